@@ -0,0 +1,46 @@
+//! Flies the autopilot over the courses for a range of seeds without a
+//! window, to check they can be got through.
+//!
+//! cargo run --release --example autoplay -- [seeds] [target score]
+
+use flappy_bird::{
+    controller::{Autopilot, Controller},
+    env::Env,
+    seed::GameSeed,
+};
+
+use std::{env, process};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let seeds: u64 = args.next().and_then(|a| a.parse().ok()).unwrap_or(20);
+    let target: usize = args.next().and_then(|a| a.parse().ok()).unwrap_or(50);
+
+    let mut env = Env::default();
+    let mut crashed = Vec::new();
+    for seed in 0..seeds {
+        env.reset(GameSeed(seed));
+        let mut autopilot = Autopilot::default();
+        loop {
+            let input = autopilot.input(env.world());
+            let (_, _, done, info) = env.step(input);
+            if done {
+                println!("seed {}: crashed with score {}", seed, info.score);
+                crashed.push(seed);
+                break;
+            }
+            if info.score >= target {
+                break;
+            }
+        }
+    }
+    println!(
+        "{} of {} courses got through to score {}",
+        seeds - crashed.len() as u64,
+        seeds,
+        target
+    );
+    if !crashed.is_empty() {
+        process::exit(1);
+    }
+}
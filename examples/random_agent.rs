@@ -0,0 +1,38 @@
+//! Plays episodes of the headless environment with an agent that flaps at
+//! random, as a starting point for real agents.
+//!
+//! cargo run --release --example random_agent -- [episodes] [seed]
+
+use flappy_bird::{env::Env, seed::GameSeed, world::InputState};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use std::env;
+
+// chance of holding flap on any tick
+const FLAP_CHANCE: f64 = 0.1;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let episodes: u64 = args.next().and_then(|a| a.parse().ok()).unwrap_or(10);
+    let seed: u64 = args.next().and_then(|a| a.parse().ok()).unwrap_or(0);
+
+    let mut env = Env::default();
+    let mut rng = StdRng::seed_from_u64(seed);
+    for episode in 0..episodes {
+        let first = env.reset(GameSeed(seed + episode));
+        println!("episode {}: start {:?}", episode, first);
+        let mut total = 0.;
+        loop {
+            let flap = rng.gen_bool(FLAP_CHANCE);
+            let (obs, reward, done, info) = env.step(InputState { flap });
+            total += reward;
+            if done {
+                println!(
+                    "episode {}: score {}, {} ticks, reward {:.1}, end {:?}",
+                    episode, info.score, info.ticks, total, obs
+                );
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Searches the courses for a range of seeds for a way through, reporting
+//! any pipe pair the bird can't get past.
+//!
+//! cargo run --release --example validate -- [seeds] [pipes] [budget]
+
+use flappy_bird::{env::Env, seed::GameSeed, validate::validate};
+
+use std::{env, process};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let seeds: u64 = args.next().and_then(|a| a.parse().ok()).unwrap_or(20);
+    let pipes: usize = args.next().and_then(|a| a.parse().ok()).unwrap_or(40);
+    let budget: u64 = args
+        .next()
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(2_000_000);
+
+    let mut env = Env::default();
+    let mut failed = Vec::new();
+    for seed in 0..seeds {
+        env.reset(GameSeed(seed));
+        if let Err(unreachable) = validate(env.world(), pipes, budget) {
+            println!("seed {}: {}", seed, unreachable);
+            failed.push(seed);
+        }
+    }
+    println!(
+        "{} of {} courses have a way through {} pipes",
+        seeds - failed.len() as u64,
+        seeds,
+        pipes
+    );
+    if !failed.is_empty() {
+        process::exit(1);
+    }
+}
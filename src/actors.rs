@@ -1,15 +1,86 @@
-use crate::{assets::Assets, util::vec_from_angle};
-
-use ggez::{
-    nalgebra as na,
-    nalgebra::{Point2, Vector2},
+use crate::{
+    collision::{Aabb, Obb, Shape, ShapeKind},
+    config::{Physics, PipeConfig},
+    entity::{Entity, ShapeParams, Sprite},
+    levels::Level,
+    powerups::{Effects, PowerUp},
+    util::vec_from_angle,
+    world::Dimensions,
 };
+
+use nalgebra::{self as na, Point2, Vector2};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-// bit of a useless trait right now
+use std::fmt;
+
+/// Anything that lives in the world. The player is in world space, every
+/// other actor is in pipe space and scrolls with the course.
 pub trait Actor {
-    fn new() -> Self;
-    fn update_pos(&mut self, dt: f32);
+    fn pos(&self) -> Point2<f32>;
+
+    fn velocity(&self) -> Vector2<f32> {
+        na::zero()
+    }
+
+    fn facing(&self) -> f32 {
+        0.
+    }
+
+    fn bbox_size(&self) -> Point2<f32>;
+
+    fn sprite(&self) -> Sprite;
+
+    /// how see through to draw it, 1 is solid
+    fn alpha(&self) -> f32 {
+        1.
+    }
+
+    /// what the bird collides with, `None` for things it flies past
+    fn shape<'a>(&self, params: ShapeParams<'a>) -> Option<Shape<'a>>;
+
+    /// move on by one tick of `dt` seconds
+    fn update(&mut self, dt: f32);
+
+    /// done with and ready to drop, by default once the course has
+    /// scrolled it off the left of the screen
+    fn despawned(&self, offset: f32) -> bool {
+        self.pos().x + self.bbox_size().x + offset <= 0.
+    }
+}
+
+/// How a flap changes the bird's speed
+#[derive(Debug, PartialEq, Eq, PartialOrd, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlapModel {
+    /// every flap sets the same upward speed, like the original
+    Classic,
+    /// like classic, but letting go early cuts the climb short
+    Variable,
+    /// flaps add to the current speed, so they stack while rising and
+    /// barely help while falling fast
+    Additive,
+}
+
+impl FlapModel {
+    pub fn next(self) -> FlapModel {
+        match self {
+            FlapModel::Classic => FlapModel::Variable,
+            FlapModel::Variable => FlapModel::Additive,
+            FlapModel::Additive => FlapModel::Classic,
+        }
+    }
+}
+
+impl fmt::Display for FlapModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FlapModel::Classic => "classic",
+            FlapModel::Variable => "variable",
+            FlapModel::Additive => "additive",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -18,6 +89,81 @@ pub struct Player {
     pub velocity: Vector2<f32>,
     pub facing: f32,
     pub bbox_size: Point2<f32>,
+    pub physics: Physics,
+    pub effects: Effects,
+}
+
+/// How a pipe moves about its resting place. Distances are in pixels and
+/// times in ticks.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub enum PipeMotion {
+    Still,
+    /// the pair bobs up and down together
+    Oscillate {
+        amplitude: f32,
+        period: f32,
+    },
+    /// the pair moves apart and back together, opening the gap wider than it
+    /// rests and closing it again
+    Breathe {
+        amplitude: f32,
+        period: f32,
+    },
+    /// the pair starts pulled apart and closes in to its resting place
+    SlideIn {
+        distance: f32,
+        duration: f32,
+    },
+}
+
+impl PipeMotion {
+    const OSCILLATE_AMPLITUDE: f32 = 60.;
+    const OSCILLATE_PERIOD: f32 = 180.;
+    /// how far each pipe of a breathing pair pulls back, as a share of the
+    /// gap
+    const BREATHE_SHARE: f32 = 1.;
+    const BREATHE_PERIOD: f32 = 150.;
+    const SLIDE_DISTANCE: f32 = 200.;
+    const SLIDE_DURATION: f32 = 240.;
+
+    fn random<R>(gap: f32, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        match rng.gen_range(0, 3) {
+            0 => PipeMotion::Oscillate {
+                amplitude: PipeMotion::OSCILLATE_AMPLITUDE,
+                period: PipeMotion::OSCILLATE_PERIOD,
+            },
+            1 => PipeMotion::Breathe {
+                amplitude: gap * PipeMotion::BREATHE_SHARE,
+                period: PipeMotion::BREATHE_PERIOD,
+            },
+            _ => PipeMotion::SlideIn {
+                distance: PipeMotion::SLIDE_DISTANCE,
+                duration: PipeMotion::SLIDE_DURATION,
+            },
+        }
+    }
+
+    /// how far the middle of the opening moves up or down from rest
+    pub fn travel(self) -> f32 {
+        match self {
+            PipeMotion::Oscillate { amplitude, .. } => amplitude,
+            _ => 0.,
+        }
+    }
+
+    /// how far down the screen the middle of the opening has moved from
+    /// rest at `age` ticks
+    pub fn drift(self, age: f32) -> f32 {
+        match self {
+            PipeMotion::Oscillate { amplitude, period } => {
+                amplitude * (age / period * 2. * std::f32::consts::PI).sin()
+            }
+            _ => 0.,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -25,6 +171,11 @@ pub struct Pipe {
     pub pos: Point2<f32>,
     pub facing: f32,
     pub bbox_size: Point2<f32>,
+    /// where the pipe sits when it isn't moving
+    pub rest: Point2<f32>,
+    pub motion: PipeMotion,
+    /// ticks since the pipe spawned
+    pub age: f32,
 }
 
 impl Player {
@@ -33,37 +184,110 @@ impl Player {
 
     pub fn flap(&mut self, dt: f32) {
         let dir = vec_from_angle(0.);
-        let flap_vec = dir * crate::FLAP_SPEED;
-        // set constant velocity on flap
-        self.velocity = flap_vec * dt;
-        // makes for more "real" physics but is not flappy bird:
-        // player.velocity += flap_vec * dt;
-        self.facing = Player::UP_ANGLE_MAX;
+        let flap_vec = dir * self.physics.flap_speed;
+        match self.physics.flap_model {
+            // set constant velocity on flap
+            FlapModel::Classic | FlapModel::Variable => self.velocity = flap_vec * dt,
+            // makes for more "real" physics but is not flappy bird
+            FlapModel::Additive => self.velocity += flap_vec * dt,
+        }
+        self.facing = self.physics.up_angle_max;
+    }
+
+    /// the flap input was let go of
+    pub fn release(&mut self, dt: f32) {
+        if let FlapModel::Variable = self.physics.flap_model {
+            // still rising faster than half a flap, cut it down to that
+            let cap = self.physics.flap_speed / 2. * dt;
+            if self.velocity.y > cap {
+                self.velocity.y = cap;
+            }
+        }
     }
 }
 
-impl Actor for Player {
-    fn new() -> Self {
+impl Player {
+    pub fn new() -> Self {
         Player {
             pos: Point2::origin(),
             velocity: na::zero(),
             bbox_size: Point2::new(14., 12.),
             facing: 0.,
+            physics: Physics::default(),
+            effects: Effects::default(),
+        }
+    }
+
+    /// bounding box at `pos`, turned with the sprite
+    pub fn obb(&self, pos: Point2<f32>) -> Obb {
+        Obb::new(pos, self.bbox_size.coords, self.facing)
+    }
+
+    /// the collision shape with the bird moved to `pos`, for checking
+    /// against actors in pipe space
+    pub fn shape_at<'a>(&self, pos: Point2<f32>, params: ShapeParams<'a>) -> Shape<'a> {
+        match (params.kinds.player, params.masks) {
+            (ShapeKind::Aabb, _) => Shape::Aabb(Aabb::new(pos, self.bbox_size.coords)),
+            (ShapeKind::Mask, Some(m)) => {
+                Shape::Mask(Obb::new(pos, m.bird.half_size(), self.facing), &m.bird)
+            }
+            _ => Shape::Obb(self.obb(pos)),
+        }
+    }
+}
+
+impl Actor for Player {
+    fn pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    fn velocity(&self) -> Vector2<f32> {
+        self.velocity
+    }
+
+    fn facing(&self) -> f32 {
+        self.facing
+    }
+
+    fn bbox_size(&self) -> Point2<f32> {
+        self.bbox_size
+    }
+
+    fn sprite(&self) -> Sprite {
+        Sprite::Bird
+    }
+
+    /// ghosts are see through
+    fn alpha(&self) -> f32 {
+        if self.effects.ghost > 0 {
+            0.5
+        } else {
+            1.
         }
     }
 
-    fn update_pos(&mut self, dt: f32) {
+    fn shape<'a>(&self, params: ShapeParams<'a>) -> Option<Shape<'a>> {
+        Some(self.shape_at(self.pos, params))
+    }
+
+    fn update(&mut self, dt: f32) {
+        // slow-mo slows the flight along with the fall, so a flap takes
+        // the bird just as high, only slower
+        let scale = self.effects.time_scale();
         let dir = vec_from_angle(0.);
-        let grav = dir * crate::FALL_SPEED;
+        let grav = dir * self.physics.fall_speed * scale;
         self.velocity -= grav * dt;
-        self.pos += self.velocity;
+        self.pos += self.velocity * scale;
         // set dir bird is facing
-        self.facing -= self.velocity.y * dt;
-        if self.facing >= Player::DOWN_ANGLE_MAX {
-            self.facing = Player::DOWN_ANGLE_MAX;
-        } else if self.facing <= Player::UP_ANGLE_MAX {
-            self.facing = Player::UP_ANGLE_MAX;
-        }
+        self.facing -= self.velocity.y * dt * scale;
+        self.facing = self
+            .facing
+            .clamp(self.physics.up_angle_max, self.physics.down_angle_max);
+    }
+
+    /// the bird stays put in world space, it's never scrolled off
+    fn despawned(&self, _offset: f32) -> bool {
+        false
     }
 }
 
@@ -86,40 +310,825 @@ impl Pipe {
     pub const MIN_RANGE: f32 = 155.;
 }
 
-impl Actor for Pipe {
-    fn new() -> Self {
+impl Pipe {
+    /// A box of `half` size around the pipe, run on past the end of the
+    /// sprite by `reach` the way it's drawn, so there's no flying round the
+    /// far end.
+    pub fn column(&self, half: Vector2<f32>, reach: f32) -> Obb {
+        Obb::new(
+            self.pos + self.away_from_gap() * (reach / 2.),
+            Vector2::new(half.x, half.y + reach / 2.),
+            self.facing,
+        )
+    }
+
+    /// the direction from the pipe's mouth along to its far end
+    pub fn away_from_gap(&self) -> Vector2<f32> {
+        Vector2::new(-self.facing.sin(), self.facing.cos())
+    }
+
+    /// the upside down one of a pair
+    pub fn is_top(&self) -> bool {
+        self.facing != 0.
+    }
+
+    /// a pipe resting at `pos`
+    pub fn at(pos: Point2<f32>, facing: f32, motion: PipeMotion) -> Self {
+        let mut pipe = Pipe {
+            rest: pos,
+            facing,
+            motion,
+            ..Pipe::new()
+        };
+        pipe.place();
+        pipe
+    }
+
+    /// put the pipe where its motion has it at its current age
+    fn place(&mut self) {
+        let wave = |amplitude: f32, period: f32| {
+            amplitude * (self.age / period * 2. * std::f32::consts::PI).sin()
+        };
+        let shift = match self.motion {
+            PipeMotion::Still => Vector2::zeros(),
+            PipeMotion::Oscillate { .. } => Vector2::new(0., self.motion.drift(self.age)),
+            PipeMotion::Breathe { amplitude, period } => {
+                // never narrower than at rest, so there's always a way through
+                self.away_from_gap() * (amplitude + wave(amplitude, period)) / 2.
+            }
+            PipeMotion::SlideIn { distance, duration } => {
+                // ease out, fast at first then settling into place
+                let left = (1. - self.age / duration).max(0.);
+                self.away_from_gap() * distance * left * left
+            }
+        };
+        self.pos = self.rest + shift;
+    }
+}
+
+impl Pipe {
+    pub fn new() -> Self {
         Pipe {
             pos: Point2::origin(),
             facing: 0.,
             bbox_size: Point2::new(26., 160.),
+            rest: Point2::origin(),
+            motion: PipeMotion::Still,
+            age: 0.,
         }
     }
+}
 
-    fn update_pos(&mut self, _dt: f32) {
-        unimplemented!();
+impl Actor for Pipe {
+    fn pos(&self) -> Point2<f32> {
+        self.pos
     }
-}
 
-pub fn gen_pipes(assets: &Assets, screen_width: f32) -> Vec<(Pipe, Pipe)> {
-    let height = f32::from(assets.bg.bg_h);
-    let pipe_h = f32::from(assets.bg.pipe_img.height()) / 2.;
-    let first_pipe = Point2::new((screen_width / 2.) + Pipe::FIRST_PIPE_X, height - pipe_h);
-    let mut rng = rand::thread_rng();
+    fn facing(&self) -> f32 {
+        self.facing
+    }
 
-    (1..=10)
-        .map(|i| {
-            let new_x = i as f32 * Pipe::BETWEEN_PIPE;
-            let opening: f32 = rng.gen_range(Pipe::MIN_RANGE, height - Pipe::MIN_RANGE);
-            // bottom pipe
-            let mut bottom_pipe = Pipe::new();
-            let pos = Point2::new(first_pipe.x + new_x, opening + Pipe::PIPE_GAP + pipe_h);
-            bottom_pipe.pos = pos;
-            // top pipe
-            let mut top_pipe = Pipe::new();
-            top_pipe.pos = Point2::new(first_pipe.x + new_x, opening - Pipe::PIPE_GAP - pipe_h);
-            top_pipe.facing = std::f32::consts::PI;
+    fn bbox_size(&self) -> Point2<f32> {
+        self.bbox_size
+    }
+
+    fn sprite(&self) -> Sprite {
+        Sprite::Pipe
+    }
 
-            (bottom_pipe, top_pipe)
+    fn shape<'a>(&self, params: ShapeParams<'a>) -> Option<Shape<'a>> {
+        let reach = params.reach;
+        Some(match (params.kinds.pipes, params.masks) {
+            (ShapeKind::Aabb, _) => Shape::Aabb(self.column(self.bbox_size.coords, reach).aabb()),
+            (ShapeKind::Mask, Some(m)) => {
+                Shape::Mask(self.column(m.pipe.half_size(), reach), &m.pipe)
+            }
+            _ => Shape::Obb(self.column(self.bbox_size.coords, reach)),
         })
-        .collect()
+    }
+
+    fn update(&mut self, dt: f32) {
+        if self.motion != PipeMotion::Still {
+            self.age += dt * crate::DESIRED_FPS as f32;
+            self.place();
+        }
+    }
+}
+
+/// Apple colours, rarer ones are worth more
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AppleKind {
+    Green,
+    Red,
+    Purple,
+}
+
+impl AppleKind {
+    /// points for collecting one
+    pub fn bonus(self) -> usize {
+        match self {
+            AppleKind::Green => 1,
+            AppleKind::Red => 2,
+            AppleKind::Purple => 5,
+        }
+    }
+
+    fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        match rng.gen_range(0, 10) {
+            0..=5 => AppleKind::Green,
+            6..=8 => AppleKind::Red,
+            _ => AppleKind::Purple,
+        }
+    }
+}
+
+/// A bonus waiting in a pipe gap, in pipe space like the pipes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Apple {
+    pub pos: Point2<f32>,
+    pub kind: AppleKind,
+    pub bbox_size: Point2<f32>,
+}
+
+impl Apple {
+    pub const APPLE_CHANCE: f32 = 0.3;
+
+    pub fn new(pos: Point2<f32>, kind: AppleKind) -> Self {
+        Apple {
+            pos,
+            kind,
+            bbox_size: Point2::new(14., 12.),
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.pos, self.bbox_size.coords)
+    }
+}
+
+impl Actor for Apple {
+    fn pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    fn bbox_size(&self) -> Point2<f32> {
+        self.bbox_size
+    }
+
+    fn sprite(&self) -> Sprite {
+        Sprite::Apple(self.kind)
+    }
+
+    fn shape<'a>(&self, _params: ShapeParams<'a>) -> Option<Shape<'a>> {
+        Some(Shape::Aabb(self.aabb()))
+    }
+
+    fn update(&mut self, _dt: f32) {}
+}
+
+/// How far the bird can get up and down in some number of ticks, found by
+/// flying a `Player` with the same physics
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Reach {
+    /// flapping as often as the timeout allows
+    pub rise: f32,
+    /// never flapping
+    pub drop: f32,
+}
+
+impl Reach {
+    /// ticks of following an opening before the bird gets to it, tried
+    /// until one lines the bounce up best
+    const LEAD_IN: usize = 40;
+    /// points in a bobbing opening's swing the bird could meet it at
+    const PHASES: usize = 8;
+
+    pub fn of(physics: Physics, ticks: f32) -> Reach {
+        let dt = 1. / crate::DESIRED_FPS as f32;
+        let mut climber = Player {
+            physics,
+            ..Player::new()
+        };
+        let mut faller = climber.clone();
+        let mut flap_timeout = 0.;
+        for _ in 0..ticks.max(0.) as u32 {
+            // the same timeout `World::apply_input` keeps
+            flap_timeout -= dt;
+            if flap_timeout < 0. {
+                flap_timeout = physics.flap_timeout;
+                climber.flap(dt);
+            }
+            climber.update(dt);
+            faller.update(dt);
+        }
+        Reach {
+            rise: climber.pos.y.max(0.),
+            drop: -faller.pos.y,
+        }
+    }
+
+    /// The least height, relative to the middle of an opening moving with
+    /// `motion`, that the bird's box sweeps through over `ticks` ticks of
+    /// following it. That's as narrow as a gap the bird spends that long in
+    /// can be. Short stays fit inside the top of one bounce, long ones take
+    /// a whole bounce or more, and a bobbing opening can pull away from the
+    /// bird however it's met.
+    pub fn squeeze(physics: Physics, ticks: f32, motion: PipeMotion) -> f32 {
+        let window = ticks.max(1.).ceil() as usize;
+        let (phases, period) = match motion {
+            PipeMotion::Oscillate { period, .. } => (Reach::PHASES, period),
+            _ => (1, 0.),
+        };
+        (0..phases)
+            .map(|phase| {
+                let age = phase as f32 * period / phases as f32;
+                (0..Reach::LEAD_IN)
+                    .map(|lead| Reach::sweep(physics, motion, age - lead as f32, lead, window))
+                    .fold(f32::MAX, f32::min)
+            })
+            .fold(0., f32::max)
+    }
+
+    /// height the bird's box sweeps through over `window` ticks, after
+    /// `lead` ticks of flapping whenever it drops to the middle of the
+    /// opening, starting level with it at the opening's `age`
+    fn sweep(physics: Physics, motion: PipeMotion, age: f32, lead: usize, window: usize) -> f32 {
+        let dt = 1. / crate::DESIRED_FPS as f32;
+        // the bird's space has y up
+        let middle = |tick: usize| -motion.drift(age + tick as f32);
+        let mut bird = Player {
+            physics,
+            ..Player::new()
+        };
+        bird.pos.y = middle(0);
+        bird.velocity.y = middle(1) - middle(0);
+        let mut flap_timeout = 0.;
+        let (mut top, mut bottom) = (f32::MIN, f32::MAX);
+        for tick in 0..lead + window {
+            flap_timeout -= dt;
+            let (here, next) = (middle(tick), middle(tick + 1));
+            if flap_timeout < 0. && bird.pos.y <= here && bird.velocity.y <= next - here {
+                flap_timeout = physics.flap_timeout;
+                bird.flap(dt);
+            }
+            bird.update(dt);
+            if tick >= lead {
+                let aabb = bird.obb(bird.pos).aabb();
+                top = top.max(aabb.max.y - next);
+                bottom = bottom.min(aabb.min.y - next);
+            }
+        }
+        top - bottom
+    }
+}
+
+/// Streams pipe pairs into the world so there is always a window of pipes
+/// ahead of the player, the course never runs out.
+///
+/// Each opening is kept within reach of the one before it. Between pairs
+/// the bird has the ticks it takes to scroll from clear of one pipe to the
+/// mouth of the next, and the next opening can be no further up or down
+/// than the bird can fly in that time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipeSpawner {
+    pub config: PipeConfig,
+    /// the bird's physics, which limit how far apart openings can be
+    pub physics: Physics,
+    // x of the next pair to spawn, in pipe space
+    next_x: f32,
+    // opening of the last pair spawned, or where the bird starts
+    last_opening: f32,
+    // how far the last pair's opening moves up or down
+    last_travel: f32,
+    // least gaps worked out so far, by physics, level speed and motion
+    least_gaps: Vec<(Physics, f32, PipeMotion, f32)>,
+}
+
+impl PipeSpawner {
+    pub fn new(dims: Dimensions, config: PipeConfig, physics: Physics) -> Self {
+        PipeSpawner {
+            config,
+            physics,
+            next_x: (dims.screen_width / 2.) + config.first_x + config.between,
+            last_opening: dims.screen_height / 2.,
+            last_travel: 0.,
+            least_gaps: Vec::new(),
+        }
+    }
+
+    /// how far the course scrolls while the bird is inside a pipe, from
+    /// when their boxes first touch to when they part, with the bird
+    /// turned whichever way makes it widest
+    fn inside() -> f32 {
+        2. * (Pipe::new().bbox_size.x + Player::new().bbox_size.coords.norm())
+    }
+
+    /// The closest pairs can be, center to center, and still leave the
+    /// bird free of one pipe before it reaches the next
+    pub fn min_between() -> f32 {
+        PipeSpawner::inside()
+    }
+
+    /// ticks the bird has to get from one opening to the next, free of
+    /// both pipes, with `spacing` between the pairs
+    fn flight_ticks(&self, spacing: f32, level: &Level) -> f32 {
+        (spacing - PipeSpawner::inside()) / (self.physics.move_speed * level.speed)
+    }
+
+    /// half the narrowest gap the bird can get through at `level`'s speed,
+    /// in a pair moving with `motion`
+    fn least_gap(&mut self, level: &Level, motion: PipeMotion) -> f32 {
+        // only bobbing changes how the bird has to fly through
+        let motion = match motion {
+            PipeMotion::Oscillate { .. } => motion,
+            _ => PipeMotion::Still,
+        };
+        let key = (self.physics, level.speed, motion);
+        if let Some(&(.., gap)) = self.least_gaps.iter().find(|l| (l.0, l.1, l.2) == key) {
+            return gap;
+        }
+        let ticks = PipeSpawner::inside() / (self.physics.move_speed * level.speed);
+        // a little over, so rounding in the flight doesn't decide a crash
+        let gap = (Reach::squeeze(self.physics, ticks, motion) + 2.) / 2.;
+        self.least_gaps
+            .push((self.physics, level.speed, motion, gap));
+        gap
+    }
+
+    /// spawn pairs until there is one waiting past the right edge of the
+    /// screen, with the odd apple or power-up in their gaps
+    pub fn fill<R>(
+        &mut self,
+        entities: &mut Vec<Entity>,
+        dims: Dimensions,
+        offset: f32,
+        level: &Level,
+        rng: &mut R,
+    ) where
+        R: Rng + ?Sized,
+    {
+        while self.next_x + offset <= dims.screen_width + self.config.between {
+            let (bottom, top) = self.spawn(dims, level, rng);
+            // the middle of the gap, pickups only go in still ones
+            let middle = Point2::new(bottom.pos.x, (bottom.pos.y + top.pos.y) / 2.);
+            let roll = if bottom.motion == PipeMotion::Still {
+                rng.gen::<f32>()
+            } else {
+                1.
+            };
+            let pickup = if roll < self.config.apple_chance {
+                Some(Entity::Apple(Apple::new(middle, AppleKind::random(rng))))
+            } else if roll < self.config.apple_chance + self.config.powerup_chance {
+                Some(Entity::PowerUp(PowerUp::new(middle, rng)))
+            } else {
+                None
+            };
+            entities.push(Entity::Pipe(bottom));
+            entities.push(Entity::Pipe(top));
+            entities.extend(pickup);
+        }
+    }
+
+    fn spawn<R>(&mut self, dims: Dimensions, level: &Level, rng: &mut R) -> (Pipe, Pipe)
+    where
+        R: Rng + ?Sized,
+    {
+        let height = dims.ground;
+        let pipe_h = dims.pipe_h;
+        let gap = self.config.gap * level.gap;
+        let margin = self.config.min_range * level.margin;
+        let x = self.next_x;
+        let spacing = self.config.between * level.spacing;
+        self.next_x += spacing;
+
+        let motion = if level.moving > 0. && rng.gen::<f32>() < level.moving {
+            PipeMotion::random(gap, rng)
+        } else {
+            PipeMotion::Still
+        };
+
+        let reach = Reach::of(self.physics, self.flight_ticks(spacing, level));
+        let (low, high) = (margin, height - margin);
+        let last = self.last_opening;
+        let within = |motion: PipeMotion| {
+            // a bobbing opening could be anywhere in its travel when the
+            // bird gets there, so that comes off what the bird can make up
+            let travel = self.last_travel + motion.travel();
+            // y grows downwards, so rising takes the opening up the screen
+            let range = (
+                low.max(last - (reach.rise - travel)),
+                high.min(last + (reach.drop - travel)),
+            );
+            if range.0 < range.1 {
+                Some(range)
+            } else {
+                None
+            }
+        };
+        // a pair that can't bob and stay in reach holds still instead
+        let range = within(motion)
+            .map(|range| (motion, range))
+            .or_else(|| within(PipeMotion::Still).map(|range| (PipeMotion::Still, range)));
+        let (motion, opening) = match range {
+            Some((motion, (low, high))) => (motion, rng.gen_range(low, high)),
+            // a config reload can move the margins out from under the last
+            // opening, so get as near it as they allow
+            None => (PipeMotion::Still, last.max(low).min(high)),
+        };
+        self.last_opening = opening;
+        self.last_travel = motion.travel();
+        // levels narrow the gap, but never past what the bird fits through
+        let gap = gap.max(self.least_gap(level, motion));
+        // bottom pipe
+        let bottom_pipe = Pipe::at(Point2::new(x, opening + gap + pipe_h), 0., motion);
+        // top pipe
+        let top_pipe = Pipe::at(
+            Point2::new(x, opening - gap - pipe_h),
+            std::f32::consts::PI,
+            motion,
+        );
+
+        (bottom_pipe, top_pipe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{levels::Levels, seed::GameSeed};
+
+    use std::ops::Range;
+
+    const DT: f32 = 1. / 60.;
+
+    fn player(flap_model: FlapModel) -> Player {
+        let mut player = Player::new();
+        player.physics.flap_model = flap_model;
+        player
+    }
+
+    /// height after each tick, flapping on the ticks in `flaps` and letting
+    /// go on the ticks in `releases`
+    fn trajectory(
+        mut player: Player,
+        ticks: usize,
+        flaps: &[usize],
+        releases: &[usize],
+    ) -> Vec<f32> {
+        (0..ticks)
+            .map(|tick| {
+                if flaps.contains(&tick) {
+                    player.flap(DT);
+                }
+                if releases.contains(&tick) {
+                    player.release(DT);
+                }
+                player.update(DT);
+                player.pos.y
+            })
+            .collect()
+    }
+
+    fn peak(heights: &[f32]) -> f32 {
+        heights.iter().cloned().fold(f32::MIN, f32::max)
+    }
+
+    /// the first `pairs` pairs of a still course, with their pickups
+    fn spawned(config: PipeConfig, pairs: usize) -> Vec<Entity> {
+        let dims = Dimensions::default();
+        let levels = Levels::default();
+        let mut spawner = PipeSpawner::new(dims, config, Physics::default());
+        let mut rng = GameSeed(7).world_rng();
+        let mut entities: Vec<Entity> = Vec::new();
+        let mut offset = 0.;
+        while entities.iter().filter(|e| e.as_pipe().is_some()).count() < pairs * 2 {
+            spawner.fill(&mut entities, dims, offset, levels.get(0), &mut rng);
+            offset -= config.between;
+        }
+        entities
+    }
+
+    #[test]
+    fn apples_spawn_in_the_gaps() {
+        let none = PipeConfig {
+            apple_chance: 0.,
+            powerup_chance: 0.,
+            ..PipeConfig::default()
+        };
+        assert!(spawned(none, 100).iter().all(|e| e.as_pipe().is_some()));
+
+        let every = PipeConfig {
+            apple_chance: 1.,
+            ..none
+        };
+        let course = spawned(every, 1000);
+        let apples: Vec<_> = course.iter().filter(|e| e.as_pipe().is_none()).collect();
+        assert!(apples.len() >= 1000);
+        // each one right in the middle of its pair's opening
+        for apple in &apples {
+            let pos = apple.pos();
+            let mut pair = course
+                .iter()
+                .filter_map(Entity::as_pipe)
+                .filter(|p| p.pos.x == pos.x);
+            let (bottom, top) = (pair.next().unwrap(), pair.next().unwrap());
+            assert_eq!(pos.y, (bottom.pos.y + top.pos.y) / 2.);
+        }
+        let count = |kind: AppleKind| {
+            apples
+                .iter()
+                .filter(|e| matches!(e, Entity::Apple(a) if a.kind == kind))
+                .count()
+        };
+        assert_eq!(
+            count(AppleKind::Green) + count(AppleKind::Red) + count(AppleKind::Purple),
+            apples.len()
+        );
+        // six in ten green, three red, one purple
+        assert!(count(AppleKind::Green) > count(AppleKind::Red));
+        assert!(count(AppleKind::Red) > count(AppleKind::Purple));
+        assert!(count(AppleKind::Purple) > 0);
+    }
+
+    #[test]
+    fn classic_flap_ignores_current_speed() {
+        let mut rising = player(FlapModel::Classic);
+        rising.velocity.y = 5.;
+        let mut falling = player(FlapModel::Classic);
+        falling.velocity.y = -5.;
+        assert_eq!(
+            trajectory(rising, 60, &[0], &[]),
+            trajectory(falling, 60, &[0], &[])
+        );
+    }
+
+    #[test]
+    fn classic_ignores_release() {
+        let held = trajectory(player(FlapModel::Classic), 60, &[0], &[]);
+        let released = trajectory(player(FlapModel::Classic), 60, &[0], &[2]);
+        assert_eq!(held, released);
+    }
+
+    #[test]
+    fn classic_flap_rises_then_falls() {
+        let heights = trajectory(player(FlapModel::Classic), 120, &[0], &[]);
+        let top = heights.iter().position(|&h| h == peak(&heights)).unwrap();
+        assert!(top > 0 && top < 119);
+        assert!(heights[..top].windows(2).all(|w| w[0] < w[1]));
+        assert!(heights[top..].windows(2).all(|w| w[0] >= w[1]));
+        assert!(heights[119] < 0.);
+    }
+
+    #[test]
+    fn variable_release_cuts_the_climb_short() {
+        let held = trajectory(player(FlapModel::Variable), 60, &[0], &[]);
+        let tapped = trajectory(player(FlapModel::Variable), 60, &[0], &[1]);
+        assert!(peak(&tapped) < peak(&held));
+        // holding is the same as classic
+        assert_eq!(held, trajectory(player(FlapModel::Classic), 60, &[0], &[]));
+    }
+
+    #[test]
+    fn variable_release_after_the_peak_changes_nothing() {
+        let held = trajectory(player(FlapModel::Variable), 90, &[0], &[]);
+        let late = trajectory(player(FlapModel::Variable), 90, &[0], &[45]);
+        assert_eq!(held, late);
+    }
+
+    #[test]
+    fn additive_flaps_stack_while_rising() {
+        let classic = trajectory(player(FlapModel::Classic), 90, &[0, 5], &[]);
+        let additive = trajectory(player(FlapModel::Additive), 90, &[0, 5], &[]);
+        assert!(peak(&additive) > peak(&classic));
+    }
+
+    #[test]
+    fn additive_flap_barely_helps_a_fast_fall() {
+        let mut classic = player(FlapModel::Classic);
+        classic.velocity.y = -8.;
+        let mut additive = player(FlapModel::Additive);
+        additive.velocity.y = -8.;
+        let classic = trajectory(classic, 60, &[0], &[]);
+        let additive = trajectory(additive, 60, &[0], &[]);
+        assert!(peak(&classic) > 0.);
+        assert!(peak(&additive) <= 0.);
+    }
+
+    /// every pair spawned on `level` for a course from `seed`, with the
+    /// spawner that made them
+    fn course(seed: u64, level: &Level) -> (PipeSpawner, Vec<(Pipe, Pipe)>) {
+        let dims = Dimensions::default();
+        let mut spawner = PipeSpawner::new(dims, PipeConfig::default(), Physics::default());
+        let mut entities = Vec::new();
+        let mut rng = crate::seed::GameSeed(seed).world_rng();
+        spawner.fill(&mut entities, dims, -10_000., level, &mut rng);
+        let pipes: Vec<Pipe> = entities
+            .iter()
+            .filter_map(Entity::as_pipe)
+            .cloned()
+            .collect();
+        let pairs = pipes
+            .chunks(2)
+            .map(|p| (p[0].clone(), p[1].clone()))
+            .collect();
+        (spawner, pairs)
+    }
+
+    /// middle of the pair's opening at rest, and how tall it is
+    fn opening(bottom: &Pipe, top: &Pipe) -> (f32, f32) {
+        let (upper, lower) = (
+            top.rest.y + top.bbox_size.y,
+            bottom.rest.y - bottom.bbox_size.y,
+        );
+        ((upper + lower) / 2., lower - upper)
+    }
+
+    fn levels() -> Vec<Level> {
+        let levels = crate::levels::Levels::default();
+        (0..=levels.level_for(usize::MAX))
+            .map(|i| levels.get(i).clone())
+            .collect()
+    }
+
+    fn openings_stay_within_reach_for(seeds: Range<u64>) {
+        for level in levels() {
+            for seed in seeds.clone() {
+                let (spawner, pairs) = course(seed, &level);
+                let spacing = spawner.config.between * level.spacing;
+                let reach = Reach::of(spawner.physics, spawner.flight_ticks(spacing, &level));
+                let mut last = (Dimensions::default().screen_height / 2., 0.);
+                for (bottom, top) in &pairs {
+                    let (middle, _) = opening(bottom, top);
+                    let travel = last.1 + bottom.motion.travel();
+                    assert!(
+                        last.0 - middle <= reach.rise - travel + 0.01,
+                        "seed {}",
+                        seed
+                    );
+                    assert!(
+                        middle - last.0 <= reach.drop - travel + 0.01,
+                        "seed {}",
+                        seed
+                    );
+                    last = (middle, bottom.motion.travel());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn openings_stay_within_reach() {
+        openings_stay_within_reach_for(0..5);
+    }
+
+    #[test]
+    #[ignore = "slow, run with cargo test --release -- --ignored"]
+    fn openings_stay_within_reach_on_many_courses() {
+        openings_stay_within_reach_for(0..200);
+    }
+
+    fn gaps_leave_room_for_the_bird_for(seeds: Range<u64>) {
+        let physics = Physics::default();
+        let bobbing = PipeMotion::Oscillate {
+            amplitude: PipeMotion::OSCILLATE_AMPLITUDE,
+            period: PipeMotion::OSCILLATE_PERIOD,
+        };
+        for level in levels() {
+            let ticks = PipeSpawner::inside() / (physics.move_speed * level.speed);
+            let still = Reach::squeeze(physics, ticks, PipeMotion::Still);
+            let bobbing = Reach::squeeze(physics, ticks, bobbing);
+            for seed in seeds.clone() {
+                for (bottom, top) in course(seed, &level).1 {
+                    let (_, gap) = opening(&bottom, &top);
+                    let least = match bottom.motion {
+                        PipeMotion::Oscillate { .. } => bobbing,
+                        _ => still,
+                    };
+                    assert!(gap >= least);
+                    assert!(gap >= 2. * Pipe::PIPE_GAP * level.gap - 0.01);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gaps_leave_room_for_the_bird() {
+        gaps_leave_room_for_the_bird_for(0..5);
+    }
+
+    #[test]
+    #[ignore = "slow, run with cargo test --release -- --ignored"]
+    fn gaps_leave_room_for_the_bird_on_many_courses() {
+        gaps_leave_room_for_the_bird_for(0..200);
+    }
+
+    #[test]
+    fn breathing_never_narrows_the_gap() {
+        let motion = PipeMotion::Breathe {
+            amplitude: 30.,
+            period: 150.,
+        };
+        let mut bottom = Pipe::at(Point2::new(0., 400.), 0., motion);
+        let mut top = Pipe::at(Point2::new(0., 0.), std::f32::consts::PI, motion);
+        let (_, rest) = opening(&bottom, &top);
+        for _ in 0..300 {
+            bottom.update(DT);
+            top.update(DT);
+            let gap = (bottom.pos.y - bottom.bbox_size.y) - (top.pos.y + top.bbox_size.y);
+            assert!(gap >= rest - 0.01);
+        }
+    }
+
+    /// how far each of a pair has moved down from rest after each of
+    /// `ticks`, bottom then top
+    fn shifts(motion: PipeMotion, ticks: &[u32]) -> Vec<(f32, f32)> {
+        let mut bottom = Pipe::at(Point2::new(0., 400.), 0., motion);
+        let mut top = Pipe::at(Point2::new(0., 0.), std::f32::consts::PI, motion);
+        let mut age = 0;
+        ticks
+            .iter()
+            .map(|&tick| {
+                for _ in age..tick {
+                    bottom.update(DT);
+                    top.update(DT);
+                }
+                age = tick;
+                assert_eq!(bottom.pos.x, bottom.rest.x);
+                (bottom.pos.y - bottom.rest.y, top.pos.y - top.rest.y)
+            })
+            .collect()
+    }
+
+    fn assert_shifts(motion: PipeMotion, expected: &[(u32, f32, f32)]) {
+        let ticks: Vec<_> = expected.iter().map(|e| e.0).collect();
+        for (&(tick, bottom, top), got) in expected.iter().zip(shifts(motion, &ticks)) {
+            let close = (got.0 - bottom).abs() < 0.01 && (got.1 - top).abs() < 0.01;
+            assert!(close, "{:?} at {}: {:?}", motion, tick, got);
+        }
+    }
+
+    #[test]
+    fn still_pipes_stay_put() {
+        assert_shifts(PipeMotion::Still, &[(0, 0., 0.), (100, 0., 0.)]);
+    }
+
+    #[test]
+    fn oscillating_pairs_move_together() {
+        let motion = PipeMotion::Oscillate {
+            amplitude: 60.,
+            period: 180.,
+        };
+        assert_eq!(motion.travel(), 60.);
+        assert_shifts(
+            motion,
+            &[
+                (0, 0., 0.),
+                (45, 60., 60.),
+                (90, 0., 0.),
+                (135, -60., -60.),
+                (180, 0., 0.),
+            ],
+        );
+    }
+
+    #[test]
+    fn breathing_pairs_open_and_close_back_to_rest() {
+        let motion = PipeMotion::Breathe {
+            amplitude: 30.,
+            period: 100.,
+        };
+        assert_eq!(motion.travel(), 0.);
+        assert_eq!(motion.drift(25.), 0.);
+        // halfway open to start, widest a quarter of the way through
+        assert_shifts(
+            motion,
+            &[
+                (0, 15., -15.),
+                (25, 30., -30.),
+                (50, 15., -15.),
+                (75, 0., 0.),
+            ],
+        );
+    }
+
+    #[test]
+    fn sliding_pairs_settle_at_rest() {
+        let motion = PipeMotion::SlideIn {
+            distance: 200.,
+            duration: 240.,
+        };
+        assert_eq!(motion.travel(), 0.);
+        assert_shifts(
+            motion,
+            &[
+                (0, 200., -200.),
+                (120, 50., -50.),
+                (240, 0., 0.),
+                (400, 0., 0.),
+            ],
+        );
+    }
 }
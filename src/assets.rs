@@ -1,12 +1,18 @@
-use crate::actors::Player;
+use crate::{
+    actors::{AppleKind, Player},
+    collision::{Mask, Masks},
+    seed::GameSeed,
+};
 
 use ggez::{
-    audio,
+    filesystem,
     graphics::{self, spritebatch::SpriteBatch, Image},
     {Context, GameResult},
 };
 use rand::Rng;
 
+use std::io::Read;
+
 #[derive(Debug, Clone)]
 pub struct BgAssets {
     pub base: SpriteBatch,
@@ -92,8 +98,11 @@ impl PlayerAssets {
         R: Rng + ?Sized,
     {
         let rand_color: usize = rng.gen_range(0, 2);
-        let color = PlayerAssets::color()[rand_color];
-        //
+        PlayerAssets::load(ctx, PlayerAssets::color()[rand_color])
+    }
+
+    /// the sprites for the bird in `color`, one of `PlayerAssets::color()`
+    pub fn load(ctx: &mut Context, color: &str) -> GameResult<Self> {
         let player_midflap = Image::new(ctx, format!("/{}bird-midflap.png", color))?;
         let player_upflap = Image::new(ctx, format!("/{}bird-upflap.png", color))?;
         let player_downflap = Image::new(ctx, format!("/{}bird-downflap.png", color))?;
@@ -109,61 +118,87 @@ impl PlayerAssets {
     pub const fn color() -> [&'static str; 3] {
         ["red", "yellow", "blue"]
     }
+
+    /// the frame for `actor`, flapping through the frames unless it's diving
+    pub fn image(&mut self, actor: &Player, frames: u64) -> &mut Image {
+        // if less than 0 point down
+        if actor.velocity.y < -3. {
+            return &mut self.player_downflap;
+        }
+        if frames <= 5 {
+            &mut self.player_upflap
+        } else if frames <= 10 {
+            &mut self.player_midflap
+        } else {
+            &mut self.player_downflap
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Assets {
     pub player: PlayerAssets,
+    /// the bird in every colour, for telling a flock apart
+    pub flock: Vec<PlayerAssets>,
     pub font: graphics::Font,
     pub gameover: Image,
     pub message: Image,
+    /// one batch per colour, indexed by `Assets::apple`
+    pub apples: [SpriteBatch; 3],
     // bg
     pub bg: BgAssets,
-    //
-    pub shot_sound: audio::SpatialSource,
-    pub hit_sound: audio::SpatialSource,
     // use up/down flap
 }
 
 impl Assets {
-    pub fn new(ctx: &mut Context) -> GameResult<Self> {
-        let mut rng = rand::thread_rng();
+    pub fn new(ctx: &mut Context, seed: GameSeed) -> GameResult<Self> {
+        let mut rng = seed.asset_rng();
         //
         let font = graphics::Font::new(ctx, "/DejaVuSerif.ttf")?;
 
         let gameover = Image::new(ctx, "/gameover.png")?;
         let message = Image::new(ctx, "/message.png")?;
-        let mut shot_sound = audio::SpatialSource::new(ctx, "/pew.ogg")?;
-        let mut hit_sound = audio::SpatialSource::new(ctx, "/boom.ogg")?;
-
-        shot_sound.set_ears([-1., 0., 0.], [1., 0., 0.]);
-        hit_sound.set_ears([-1., 0., 0.], [1., 0., 0.]);
+        let apples = [
+            SpriteBatch::new(Image::new(ctx, "/apple_green.png")?),
+            SpriteBatch::new(Image::new(ctx, "/apple_red.png")?),
+            SpriteBatch::new(Image::new(ctx, "/apple_purple.png")?),
+        ];
 
         Ok(Assets {
             player: PlayerAssets::new(ctx, &mut rng)?,
+            flock: PlayerAssets::color()
+                .iter()
+                .map(|color| PlayerAssets::load(ctx, color))
+                .collect::<GameResult<_>>()?,
             font,
             message,
             gameover,
-            shot_sound,
-            hit_sound,
+            apples,
             bg: BgAssets::new(ctx, &mut rng)?,
         })
     }
 
-    pub fn player_image(&mut self, actor: &Player, frames: u64) -> &mut Image {
-        // if less than 0 point down
-        if actor.velocity.y < -3. {
-            return &mut self.player.player_downflap;
-        }
-        if frames <= 5 {
-            &mut self.player.player_upflap
-        } else if frames <= 10 {
-            &mut self.player.player_midflap
-        } else {
-            &mut self.player.player_downflap
+    /// Collision masks from the sprites' alpha. Every colour of bird and
+    /// pipe has the same outline, so one of each will do.
+    pub fn masks(ctx: &mut Context) -> GameResult<Masks> {
+        let bird = load_mask(ctx, "/redbird-midflap.png")?;
+        let mut pipe = load_mask(ctx, "/pipe-green.png")?;
+        pipe.repeat_last_row = true;
+        Ok(Masks { bird, pipe })
+    }
+
+    pub fn apple(&mut self, kind: AppleKind) -> &mut SpriteBatch {
+        match kind {
+            AppleKind::Green => &mut self.apples[0],
+            AppleKind::Red => &mut self.apples[1],
+            AppleKind::Purple => &mut self.apples[2],
         }
     }
 
+    pub fn player_image(&mut self, actor: &Player, frames: u64) -> &mut Image {
+        self.player.image(actor, frames)
+    }
+
     pub fn countdown(&mut self, frames: u64) -> Option<&mut Image> {
         if frames <= 60 {
             Some(&mut self.bg.numbers[3])
@@ -176,3 +211,14 @@ impl Assets {
         }
     }
 }
+
+fn load_mask(ctx: &mut Context, path: &str) -> GameResult<Mask> {
+    let mut bytes = Vec::new();
+    filesystem::open(ctx, path)?.read_to_end(&mut bytes)?;
+    let img = image::load_from_memory(&bytes)?.to_rgba();
+    Ok(Mask::from_rgba(
+        img.width() as usize,
+        img.height() as usize,
+        &img.into_raw(),
+    ))
+}
@@ -0,0 +1,208 @@
+use crate::{util::user_config_file, world::Event};
+
+use ggez::{
+    audio::{SoundSource, SpatialSource},
+    nalgebra::Point2,
+    Context, GameError, GameResult,
+};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+/// Volumes run from 0 to 1, the sound effects play at their product
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master_volume: 1.,
+            sfx_volume: 0.7,
+            muted: false,
+        }
+    }
+}
+
+/// Plays sound effects for world events, panned by where on screen they
+/// happened
+#[derive(Debug)]
+pub struct Audio {
+    pub settings: AudioSettings,
+    flap: SpatialSource,
+    point: SpatialSource,
+    hit: SpatialSource,
+}
+
+impl AudioSettings {
+    const FILE: &'static str = "audio.toml";
+    const VOLUME_STEP: f32 = 0.1;
+
+    /// load the settings from the user config directory, a missing file
+    /// is the defaults
+    pub fn load(ctx: &Context) -> GameResult<Self> {
+        let path = user_config_file(ctx, AudioSettings::FILE)?;
+        if !path.exists() {
+            return Ok(AudioSettings::default());
+        }
+        AudioSettings::from_toml(&fs::read_to_string(&path)?)
+            .map_err(|e| GameError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, ctx: &Context) -> GameResult<()> {
+        let path = user_config_file(ctx, AudioSettings::FILE)?;
+        fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    /// the settings in `s`, volumes out of range are brought back into it
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        let settings: AudioSettings = toml::from_str(s).map_err(|e| e.to_string())?;
+        Ok(AudioSettings {
+            master_volume: in_range(settings.master_volume),
+            sfx_volume: in_range(settings.sfx_volume),
+            ..settings
+        })
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("audio settings are valid toml")
+    }
+
+    /// the volume sound effects play at, nothing while muted
+    pub fn volume(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.master_volume * self.sfx_volume
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+    }
+
+    pub fn volume_up(&mut self) {
+        self.master_volume = in_range(self.master_volume + AudioSettings::VOLUME_STEP);
+    }
+
+    pub fn volume_down(&mut self) {
+        self.master_volume = in_range(self.master_volume - AudioSettings::VOLUME_STEP);
+    }
+
+    pub fn sfx_up(&mut self) {
+        self.sfx_volume = in_range(self.sfx_volume + AudioSettings::VOLUME_STEP);
+    }
+
+    pub fn sfx_down(&mut self) {
+        self.sfx_volume = in_range(self.sfx_volume - AudioSettings::VOLUME_STEP);
+    }
+}
+
+// NaN from a hand edited file ends up silent rather than poisoning the mix
+fn in_range(volume: f32) -> f32 {
+    if volume.is_nan() {
+        0.
+    } else {
+        volume.clamp(0., 1.)
+    }
+}
+
+impl Audio {
+    pub fn new(ctx: &mut Context, settings: AudioSettings) -> GameResult<Self> {
+        let mut flap = SpatialSource::new(ctx, "/pew.ogg")?;
+        let mut point = SpatialSource::new(ctx, "/pew.ogg")?;
+        let mut hit = SpatialSource::new(ctx, "/boom.ogg")?;
+        // same sample as the flap, but higher so they can be told apart
+        point.set_pitch(1.5);
+
+        for sound in [&mut flap, &mut point, &mut hit].iter_mut() {
+            sound.set_ears([-1., 0., 0.], [1., 0., 0.]);
+        }
+
+        Ok(Audio {
+            settings,
+            flap,
+            point,
+            hit,
+        })
+    }
+
+    pub fn play(&mut self, event: Event, screen_width: f32) -> GameResult<()> {
+        if self.settings.muted {
+            return Ok(());
+        }
+        let volume = self.settings.volume();
+        let (sound, pos) = match event {
+            Event::Flap(pos) => (&mut self.flap, pos),
+            Event::Point(pos) | Event::Collect(pos, _) | Event::PowerUp(pos, _) => {
+                (&mut self.point, pos)
+            }
+            Event::Hit(pos) => (&mut self.hit, pos),
+        };
+        sound.set_volume(volume);
+        sound.set_position(pan(pos, screen_width));
+        sound.play()
+    }
+}
+
+/// emitter position for something at `pos` on screen, between the ears
+/// when it's in the middle and out past one of them at the edges
+fn pan(pos: Point2<f32>, screen_width: f32) -> [f32; 3] {
+    let x = (pos.x / screen_width) * 2. - 1.;
+    [x * 2., 0., 1.]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volumes_stay_between_silent_and_full() {
+        let mut settings = AudioSettings::default();
+        for _ in 0..20 {
+            settings.volume_up();
+            settings.sfx_up();
+        }
+        assert_eq!((settings.master_volume, settings.sfx_volume), (1., 1.));
+        for _ in 0..20 {
+            settings.volume_down();
+            settings.sfx_down();
+        }
+        assert_eq!((settings.master_volume, settings.sfx_volume), (0., 0.));
+
+        // the two mix, and one step is a tenth
+        let mut settings = AudioSettings::default();
+        settings.sfx_down();
+        assert!((settings.volume() - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn muting_silences_without_losing_the_volume() {
+        let mut settings = AudioSettings::default();
+        settings.toggle_mute();
+        assert_eq!(settings.volume(), 0.);
+        settings.volume_up();
+        settings.toggle_mute();
+        assert!((settings.volume() - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn settings_round_trip_through_toml() {
+        let mut settings = AudioSettings::default();
+        settings.sfx_down();
+        settings.toggle_mute();
+        assert_eq!(AudioSettings::from_toml(&settings.to_toml()), Ok(settings));
+
+        // left out is the default, out of range is brought back in
+        let loaded = AudioSettings::from_toml("master_volume = 3.0\nsfx_volume = -1.0\n").unwrap();
+        assert_eq!(loaded.master_volume, 1.);
+        assert_eq!(loaded.sfx_volume, 0.);
+        assert!(!loaded.muted);
+        assert!(AudioSettings::from_toml("muted = 3").is_err());
+    }
+}
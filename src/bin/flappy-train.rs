@@ -0,0 +1,167 @@
+//! Evolves brains to fly the bird, without a window, and saves the best one
+//! for `flappy_bird --brain` to load.
+//!
+//! cargo run --release --bin flappy-train -- [OPTIONS]
+
+use flappy_bird::{
+    brain::Brain,
+    error::{GameError, GameResult},
+    evolve::{self, Fitness, Population, Settings},
+    seed::GameSeed,
+};
+
+use std::{env, path::PathBuf, str::FromStr};
+
+/// Command line options
+#[derive(Debug, Clone)]
+struct Args {
+    settings: Settings,
+    generations: usize,
+    seed: GameSeed,
+    out: PathBuf,
+    // where each generation is saved, to watch as a flock
+    generation_out: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse<I>(args: I) -> GameResult<Args>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Args {
+            settings: Settings::default(),
+            generations: 50,
+            seed: GameSeed(0),
+            out: "brain.toml".into(),
+            generation_out: None,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let settings = &mut parsed.settings;
+            match arg.as_str() {
+                "--generations" => parsed.generations = number(&arg, args.next())?,
+                "--population" => settings.population = number(&arg, args.next())?,
+                "--courses" => settings.courses = number(&arg, args.next())?,
+                "--max-ticks" => settings.max_ticks = number(&arg, args.next())?,
+                "--threads" => settings.threads = number(&arg, args.next())?,
+                "--elite" => settings.elite = number(&arg, args.next())?,
+                "--mutation-rate" => settings.mutation_rate = number(&arg, args.next())?,
+                "--mutation-strength" => settings.mutation_strength = number(&arg, args.next())?,
+                "--seed" => parsed.seed = number(&arg, args.next())?,
+                "--out" => parsed.out = value(&arg, args.next())?.into(),
+                "--save-generation" => {
+                    parsed.generation_out = Some(value(&arg, args.next())?.into())
+                }
+                "-h" | "--help" => {
+                    print_usage();
+                    std::process::exit(0);
+                }
+                _ => {
+                    return Err(GameError::ConfigError(format!(
+                        "unknown argument {:?}, try --help",
+                        arg
+                    )))
+                }
+            }
+        }
+        parsed.settings.check().map_err(GameError::ConfigError)?;
+        Ok(parsed)
+    }
+}
+
+fn value(flag: &str, val: Option<String>) -> GameResult<String> {
+    val.ok_or_else(|| GameError::ConfigError(format!("{} needs a value", flag)))
+}
+
+fn number<T>(flag: &str, val: Option<String>) -> GameResult<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let val = value(flag, val)?;
+    val.parse()
+        .map_err(|e| GameError::ConfigError(format!("invalid {} {:?}: {}", flag, val, e)))
+}
+
+fn print_usage() {
+    let defaults = Settings::default();
+    println!("Usage: flappy-train [OPTIONS]");
+    println!();
+    println!("  --generations <n>         generations to breed (50)");
+    println!(
+        "  --population <n>          brains in each generation ({})",
+        defaults.population
+    );
+    println!(
+        "  --courses <n>             courses each brain flies a generation ({})",
+        defaults.courses
+    );
+    println!(
+        "  --max-ticks <n>           longest a run goes on ({})",
+        defaults.max_ticks
+    );
+    println!(
+        "  --threads <n>             threads to fly the brains on ({})",
+        defaults.threads
+    );
+    println!(
+        "  --elite <n>               fittest brains kept as they are ({})",
+        defaults.elite
+    );
+    println!(
+        "  --mutation-rate <x>       chance of each weight being nudged ({})",
+        defaults.mutation_rate
+    );
+    println!(
+        "  --mutation-strength <x>   most a weight is nudged by ({})",
+        defaults.mutation_strength
+    );
+    println!("  --seed <n>                seed for the courses and breeding (0)");
+    println!(
+        "  --out <file>              where to save the brain that does best on {} held out courses (brain.toml)",
+        Population::HELD_OUT
+    );
+    println!("  --save-generation <file>  save each generation, for flappy_bird --flock");
+    println!("  -h, --help                print this message");
+}
+
+fn main() -> GameResult {
+    let args = Args::parse(env::args().skip(1))?;
+    let mut population = Population::new(args.settings, args.seed);
+    // each generation flies new courses, so champions are compared on
+    // courses none of them trained on
+    let held_out = population.held_out();
+    let mut best: Option<Fitness> = None;
+
+    for _ in 0..args.generations {
+        let fitness = population.evaluate();
+        let (champion, top) = fitness
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total().partial_cmp(&b.1.total()).unwrap())
+            .map(|(i, &f)| (i, f))
+            .unwrap_or_default();
+        let mean = fitness.iter().map(|f| f.total()).sum::<f32>() / fitness.len() as f32;
+        print!(
+            "generation {:>3}: best {:>7.1} (score {:>3}), mean {:>7.1}",
+            population.generation,
+            top.total(),
+            top.score,
+            mean
+        );
+        let brain = &population.brains[champion];
+        let tested = evolve::evaluate(brain, &held_out, args.settings.max_ticks);
+        print!(", held out {:>7.1}", tested.total());
+        if best.is_none_or(|best| tested.total() > best.total()) {
+            brain.save(&args.out)?;
+            best = Some(tested);
+            print!(", saved to {}", args.out.display());
+        }
+        println!();
+        if let Some(ref path) = args.generation_out {
+            Brain::save_generation(&population.brains, path)?;
+        }
+        population.breed(&fitness);
+    }
+    Ok(())
+}
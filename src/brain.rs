@@ -0,0 +1,298 @@
+use crate::{
+    controller::Controller,
+    env::Observation,
+    error::{GameError, GameResult},
+    world::{InputState, World},
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use std::{fs, path::Path};
+
+/// A small feed forward network that decides when to flap, and the genome
+/// `flappy-train` evolves. Each node adds a bias to a weighted sum of the
+/// layer before and squashes it with tanh, and the bird flaps while the one
+/// output is above zero.
+///
+/// Saved as toml, so a trained brain can be read and tinkered with by hand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Brain {
+    /// nodes in each layer, from the inputs to the output
+    pub layers: Vec<usize>,
+    /// each node's bias followed by its weights, node by node and layer by
+    /// layer
+    pub weights: Vec<f32>,
+}
+
+impl Brain {
+    /// nodes between the inputs and the output
+    pub const HIDDEN: usize = 6;
+    /// what each input is, for drawing
+    pub const INPUTS: [&'static str; Observation::SIZE] =
+        ["height", "speed", "pipe", "gap top", "gap bottom"];
+
+    /// a brain with every weight picked at random
+    pub fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let layers = vec![Observation::SIZE, Brain::HIDDEN, 1];
+        let weights = (0..Brain::weight_count(&layers))
+            .map(|_| rng.gen_range(-1., 1.))
+            .collect();
+        Brain { layers, weights }
+    }
+
+    fn weight_count(layers: &[usize]) -> usize {
+        layers.windows(2).map(|w| (w[0] + 1) * w[1]).sum()
+    }
+
+    /// where node `to` of `layer` starts in the weights, at its bias
+    fn block(&self, layer: usize, to: usize) -> usize {
+        Brain::weight_count(&self.layers[..layer]) + to * (self.layers[layer - 1] + 1)
+    }
+
+    /// the weight from node `from` of the layer before `layer` into its
+    /// node `to`
+    pub fn weight(&self, layer: usize, to: usize, from: usize) -> f32 {
+        self.weights[self.block(layer, to) + 1 + from]
+    }
+
+    /// The inputs for `world`, scaled to a few units around zero: how high
+    /// the bird is, how fast it's falling, how far off the next pair is and
+    /// how far above and below the bird the edges of its gap are.
+    pub fn inputs(world: &World) -> [f32; Observation::SIZE] {
+        let obs = Observation::of(world);
+        let ground = world.dims.ground;
+        [
+            obs.player_y / ground * 2. - 1.,
+            obs.player_velocity / 10.,
+            obs.pipe_distance / world.dims.screen_width,
+            (obs.gap_top - obs.player_y) / 100.,
+            (obs.gap_bottom - obs.player_y) / 100.,
+        ]
+    }
+
+    /// every node's value for `inputs`, layer by layer from the inputs
+    pub fn think(&self, inputs: &[f32]) -> Vec<Vec<f32>> {
+        let mut values = vec![inputs.to_vec()];
+        for layer in 1..self.layers.len() {
+            let last = &values[layer - 1];
+            let next = (0..self.layers[layer])
+                .map(|to| {
+                    let bias = self.weights[self.block(layer, to)];
+                    let sum = last.iter().enumerate().fold(bias, |sum, (from, v)| {
+                        sum + v * self.weight(layer, to, from)
+                    });
+                    sum.tanh()
+                })
+                .collect();
+            values.push(next);
+        }
+        values
+    }
+
+    /// whether the output is above zero for `inputs`
+    pub fn flaps(&self, inputs: &[f32]) -> bool {
+        self.think(inputs).last().is_some_and(|out| out[0] > 0.)
+    }
+
+    /// nudge each weight, with a chance of `rate`, by up to `strength`
+    /// either way
+    pub fn mutate<R>(&mut self, rate: f32, strength: f32, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        for weight in self.weights.iter_mut() {
+            if rng.gen::<f32>() < rate {
+                *weight += rng.gen_range(-strength, strength);
+            }
+        }
+    }
+
+    /// a child taking each weight from one parent or the other
+    pub fn crossover<R>(&self, other: &Brain, rng: &mut R) -> Brain
+    where
+        R: Rng + ?Sized,
+    {
+        let weights = self
+            .weights
+            .iter()
+            .zip(&other.weights)
+            .map(|(&a, &b)| if rng.gen() { a } else { b })
+            .collect();
+        Brain {
+            layers: self.layers.clone(),
+            weights,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> GameResult<Brain> {
+        let path = path.as_ref();
+        let brain: Brain = read_toml(path)?;
+        brain.check().map_err(|e| path_error(path, e))?;
+        Ok(brain)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> GameResult<()> {
+        write_toml(self, path)
+    }
+
+    /// a whole generation saved by `save_generation`
+    pub fn load_generation<P: AsRef<Path>>(path: P) -> GameResult<Vec<Brain>> {
+        let path = path.as_ref();
+        let generation: Generation = read_toml(path)?;
+        if generation.brains.is_empty() {
+            return Err(path_error(path, "no brains".to_string()));
+        }
+        for brain in &generation.brains {
+            brain.check().map_err(|e| path_error(path, e))?;
+        }
+        Ok(generation.brains)
+    }
+
+    /// save `brains` together, for flying as a flock
+    pub fn save_generation<P: AsRef<Path>>(brains: &[Brain], path: P) -> GameResult<()> {
+        let generation = Generation {
+            brains: brains.to_vec(),
+        };
+        write_toml(&generation, path)
+    }
+
+    /// whether the layers fit the game and the weights fit the layers
+    fn check(&self) -> Result<(), String> {
+        if self.layers.first() != Some(&Observation::SIZE) || self.layers.last() != Some(&1) {
+            return Err(format!(
+                "layers must start with {} inputs and end with 1 output",
+                Observation::SIZE
+            ));
+        }
+        if self.weights.len() != Brain::weight_count(&self.layers) {
+            return Err(format!(
+                "layers {:?} need {} weights, found {}",
+                self.layers,
+                Brain::weight_count(&self.layers),
+                self.weights.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A generation of brains in one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Generation {
+    brains: Vec<Brain>,
+}
+
+fn path_error(path: &Path, e: String) -> GameError {
+    GameError::ConfigError(format!("{}: {}", path.display(), e))
+}
+
+fn read_toml<T>(path: &Path) -> GameResult<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let s = fs::read_to_string(path).map_err(|e| path_error(path, e.to_string()))?;
+    toml::from_str(&s).map_err(|e| path_error(path, e.to_string()))
+}
+
+fn write_toml<T: Serialize, P: AsRef<Path>>(value: &T, path: P) -> GameResult<()> {
+    let s = toml::to_string(value).map_err(|e| GameError::ConfigError(e.to_string()))?;
+    fs::write(path, s)?;
+    Ok(())
+}
+
+impl Controller for Brain {
+    fn input(&mut self, world: &World) -> InputState {
+        InputState {
+            flap: self.flaps(&Brain::inputs(world)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::GameSeed;
+
+    use std::path::PathBuf;
+
+    /// a file for this test run only, removed when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("flappy-{}-{}.toml", name, std::process::id());
+            TempFile(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn weights_go_bias_first_node_by_node() {
+        let brain = Brain {
+            layers: vec![2, 2, 1],
+            weights: (0..9).map(|i| i as f32 / 10.).collect(),
+        };
+        assert_eq!(Brain::weight_count(&brain.layers), 9);
+        assert_eq!(brain.weight(1, 0, 0), 0.1);
+        assert_eq!(brain.weight(1, 0, 1), 0.2);
+        assert_eq!(brain.weight(1, 1, 0), 0.4);
+        assert_eq!(brain.weight(2, 0, 1), 0.8);
+
+        let values = brain.think(&[1., -1.]);
+        let hidden = [(0. + 0.1 - 0.2_f32).tanh(), (0.3 + 0.4 - 0.5_f32).tanh()];
+        let out = (0.6 + 0.7 * hidden[0] + 0.8 * hidden[1]).tanh();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[0], [1., -1.]);
+        assert!((values[1][0] - hidden[0]).abs() < 1e-6);
+        assert!((values[1][1] - hidden[1]).abs() < 1e-6);
+        assert!((values[2][0] - out).abs() < 1e-6);
+        assert!(brain.flaps(&[1., -1.]));
+    }
+
+    #[test]
+    fn brains_round_trip_through_files() {
+        let mut rng = GameSeed(1).world_rng();
+        let brain = Brain::random(&mut rng);
+        let file = TempFile::new("brain");
+        brain.save(&file.0).unwrap();
+        assert_eq!(Brain::load(&file.0).unwrap(), brain);
+
+        let brains = vec![brain, Brain::random(&mut rng)];
+        let file = TempFile::new("generation");
+        Brain::save_generation(&brains, &file.0).unwrap();
+        assert_eq!(Brain::load_generation(&file.0).unwrap(), brains);
+    }
+
+    #[test]
+    fn brains_must_fit_the_game() {
+        let mut brain = Brain::random(&mut GameSeed(1).world_rng());
+        assert_eq!(brain.check(), Ok(()));
+
+        brain.weights.pop();
+        assert!(brain.check().unwrap_err().contains("weights"));
+
+        let mut brain = Brain {
+            layers: vec![3, 1],
+            weights: vec![0.; 4],
+        };
+        assert!(brain.check().unwrap_err().contains("inputs"));
+        brain.layers = vec![Observation::SIZE, 2];
+        assert!(brain.check().is_err());
+
+        // and loading turns them away
+        let file = TempFile::new("bad-brain");
+        fs::write(&file.0, "layers = [3, 1]\nweights = [0.0, 0.0, 0.0, 0.0]\n").unwrap();
+        assert!(Brain::load(&file.0).is_err());
+        fs::write(&file.0, "brains = []\n").unwrap();
+        assert!(Brain::load_generation(&file.0).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::seed::GameSeed;
+
+use ggez::{GameError, GameResult};
+
+use std::path::PathBuf;
+
+/// Command line options
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    pub seed: Option<GameSeed>,
+    pub replay: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    /// let the autopilot fly, restarting after each crash
+    pub autoplay: bool,
+    /// let a brain saved by `flappy-train` fly, the same way
+    pub brain: Option<PathBuf>,
+    /// let a whole generation saved by `flappy-train` fly the course together
+    pub flock: Option<PathBuf>,
+    /// two players race on the same course, each with their own flap
+    pub race: bool,
+}
+
+impl Args {
+    pub fn parse<I>(args: I) -> GameResult<Args>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let seed = value(&arg, args.next())?;
+                    parsed.seed = Some(seed.parse().map_err(|e| {
+                        GameError::ConfigError(format!("invalid seed {:?}: {}", seed, e))
+                    })?);
+                }
+                "--config" => {
+                    parsed.config = Some(value(&arg, args.next())?.into());
+                }
+                "--replay" => {
+                    parsed.replay = Some(value(&arg, args.next())?.into());
+                }
+                "--autoplay" => parsed.autoplay = true,
+                "--brain" => {
+                    parsed.brain = Some(value(&arg, args.next())?.into());
+                }
+                "--flock" => {
+                    parsed.flock = Some(value(&arg, args.next())?.into());
+                }
+                "--race" => parsed.race = true,
+                "-h" | "--help" => {
+                    print_usage();
+                    std::process::exit(0);
+                }
+                _ => {
+                    return Err(GameError::ConfigError(format!(
+                        "unknown argument {:?}, try --help",
+                        arg
+                    )))
+                }
+            }
+        }
+        let watching = parsed.replay.is_some()
+            || parsed.autoplay
+            || parsed.brain.is_some()
+            || parsed.flock.is_some();
+        if parsed.race && watching {
+            return Err(GameError::ConfigError(
+                "--race is for two players, not a replay or something flying itself".to_string(),
+            ));
+        }
+        Ok(parsed)
+    }
+}
+
+fn value(flag: &str, val: Option<String>) -> GameResult<String> {
+    val.ok_or_else(|| GameError::ConfigError(format!("{} needs a value", flag)))
+}
+
+fn print_usage() {
+    println!("Usage: flappy_bird [OPTIONS]");
+    println!();
+    println!("  --seed <n>      play the course generated from seed <n>");
+    println!("  --config <file> load gameplay settings from a toml file, reloaded on change");
+    println!("  --replay <file> watch a replay recorded by an earlier run");
+    println!("  --autoplay      watch the autopilot play, as a demo");
+    println!("  --brain <file>  watch a brain trained by flappy-train play");
+    println!("  --flock <file>  watch a generation saved by flappy-train play together");
+    println!("  --race          two players race on the same course, the second flaps with L");
+    println!("  -h, --help      print this message");
+}
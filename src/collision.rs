@@ -0,0 +1,398 @@
+use nalgebra::{Point2, Vector2};
+use serde::{Deserialize, Serialize};
+
+/// Which test an actor is checked with
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShapeKind {
+    /// axis aligned box, ignores rotation
+    Aabb,
+    /// box rotated with the sprite
+    Obb,
+    /// the sprite's opaque pixels, falls back to `Obb` when the sprites
+    /// aren't loaded (headless)
+    Mask,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Aabb {
+    pub min: Point2<f32>,
+    pub max: Point2<f32>,
+}
+
+impl Aabb {
+    pub fn new(center: Point2<f32>, half: Vector2<f32>) -> Self {
+        Aabb {
+            min: center - half,
+            max: center + half,
+        }
+    }
+
+    /// touching edges count as a hit
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    pub fn contains(&self, p: Point2<f32>) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+    }
+}
+
+/// Box rotated by `angle` about its center, the same way ggez rotates a
+/// sprite drawn with a (0.5, 0.5) offset
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Obb {
+    pub center: Point2<f32>,
+    pub half: Vector2<f32>,
+    pub angle: f32,
+}
+
+impl Obb {
+    pub fn new(center: Point2<f32>, half: Vector2<f32>, angle: f32) -> Self {
+        Obb {
+            center,
+            half,
+            angle,
+        }
+    }
+
+    /// the box's local x and y axes
+    fn axes(&self) -> [Vector2<f32>; 2] {
+        let (sin, cos) = self.angle.sin_cos();
+        [Vector2::new(cos, sin), Vector2::new(-sin, cos)]
+    }
+
+    /// half the length of the box projected onto `axis`
+    fn radius(&self, axis: Vector2<f32>) -> f32 {
+        let [x, y] = self.axes();
+        self.half.x * x.dot(&axis).abs() + self.half.y * y.dot(&axis).abs()
+    }
+
+    /// `p` in the box's local space, relative to its center
+    pub fn to_local(self, p: Point2<f32>) -> Vector2<f32> {
+        let d = p - self.center;
+        let [x, y] = self.axes();
+        Vector2::new(d.dot(&x), d.dot(&y))
+    }
+
+    pub fn to_world(self, local: Vector2<f32>) -> Point2<f32> {
+        let [x, y] = self.axes();
+        self.center + x * local.x + y * local.y
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let half = Vector2::new(
+            self.radius(Vector2::new(1., 0.)),
+            self.radius(Vector2::new(0., 1.)),
+        );
+        Aabb::new(self.center, half)
+    }
+
+    /// separating axis test, touching edges count as a hit
+    pub fn intersects(&self, other: &Obb) -> bool {
+        let d = other.center - self.center;
+        let [ax, ay] = self.axes();
+        let [bx, by] = other.axes();
+        [ax, ay, bx, by]
+            .iter()
+            .all(|&axis| d.dot(&axis).abs() <= self.radius(axis) + other.radius(axis))
+    }
+
+    pub fn contains(&self, p: Point2<f32>) -> bool {
+        let local = self.to_local(p);
+        local.x.abs() <= self.half.x && local.y.abs() <= self.half.y
+    }
+}
+
+/// Opaque pixels of a sprite
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    solid: Vec<bool>,
+    /// rows past the bottom repeat the last one, for pipes which are drawn
+    /// running on off the screen
+    pub repeat_last_row: bool,
+}
+
+impl Mask {
+    /// any pixel with alpha at or above this is solid
+    const ALPHA_CUTOFF: u8 = 128;
+
+    pub fn from_rgba(width: usize, height: usize, rgba: &[u8]) -> Self {
+        let solid = rgba
+            .chunks(4)
+            .take(width * height)
+            .map(|px| px[3] >= Mask::ALPHA_CUTOFF)
+            .collect();
+        Mask {
+            width,
+            height,
+            solid,
+            repeat_last_row: false,
+        }
+    }
+
+    pub fn half_size(&self) -> Vector2<f32> {
+        Vector2::new(self.width as f32 / 2., self.height as f32 / 2.)
+    }
+
+    /// pixel at `(x, y)` counting from the top left
+    pub fn is_solid(&self, x: f32, y: f32) -> bool {
+        if x < 0. || y < 0. || x >= self.width as f32 || self.height == 0 {
+            return false;
+        }
+        let mut row = y as usize;
+        if row >= self.height {
+            if !self.repeat_last_row {
+                return false;
+            }
+            row = self.height - 1;
+        }
+        self.solid[row * self.width + x as usize]
+    }
+}
+
+/// Sprite masks for the actors that can use `ShapeKind::Mask`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Masks {
+    pub bird: Mask,
+    pub pipe: Mask,
+}
+
+/// An actor's collision shape placed in the world
+#[derive(Debug, Copy, Clone)]
+pub enum Shape<'a> {
+    Aabb(Aabb),
+    Obb(Obb),
+    /// a mask placed by the box it fills from the top left, the box may be
+    /// taller than the mask if it repeats its last row
+    Mask(Obb, &'a Mask),
+}
+
+impl<'a> Shape<'a> {
+    pub fn aabb(&self) -> Aabb {
+        match *self {
+            Shape::Aabb(aabb) => aabb,
+            Shape::Obb(ref obb) | Shape::Mask(ref obb, _) => obb.aabb(),
+        }
+    }
+
+    pub fn contains(&self, p: Point2<f32>) -> bool {
+        match *self {
+            Shape::Aabb(ref aabb) => aabb.contains(p),
+            Shape::Obb(ref obb) => obb.contains(p),
+            Shape::Mask(ref obb, mask) => {
+                let local = obb.to_local(p) + obb.half;
+                mask.is_solid(local.x, local.y)
+            }
+        }
+    }
+
+    /// Boxes are tested exactly. Once a mask is involved the points of
+    /// `self` are sampled a pixel apart against `other`, so pass the
+    /// smaller shape first.
+    pub fn intersects(&self, other: &Shape) -> bool {
+        if !self.aabb().intersects(&other.aabb()) {
+            return false;
+        }
+        match (self, other) {
+            (Shape::Aabb(_), Shape::Aabb(_)) => true,
+            (Shape::Obb(a), Shape::Obb(b)) => a.intersects(b),
+            (Shape::Obb(a), Shape::Aabb(b)) | (Shape::Aabb(b), Shape::Obb(a)) => {
+                a.intersects(&Obb::new(center(b), (b.max - b.min) / 2., 0.))
+            }
+            _ => self.points().into_iter().any(|p| other.contains(p)),
+        }
+    }
+
+    /// sample points a pixel apart covering the shape
+    fn points(&self) -> Vec<Point2<f32>> {
+        let obb = match *self {
+            Shape::Aabb(ref aabb) => Obb::new(center(aabb), (aabb.max - aabb.min) / 2., 0.),
+            Shape::Obb(obb) | Shape::Mask(obb, _) => obb,
+        };
+        let w = (obb.half.x * 2.).ceil() as usize;
+        let h = (obb.half.y * 2.).ceil() as usize;
+        (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .filter(|&(x, y)| match *self {
+                Shape::Mask(_, mask) => mask.is_solid(x as f32 + 0.5, y as f32 + 0.5),
+                _ => true,
+            })
+            .map(|(x, y)| obb.to_world(Vector2::new(x as f32 + 0.5, y as f32 + 0.5) - obb.half))
+            .collect()
+    }
+}
+
+fn center(aabb: &Aabb) -> Point2<f32> {
+    Point2::from((aabb.min.coords + aabb.max.coords) / 2.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_4, PI};
+
+    fn aabb(x: f32, y: f32, hw: f32, hh: f32) -> Aabb {
+        Aabb::new(Point2::new(x, y), Vector2::new(hw, hh))
+    }
+
+    /// a pipe standing on the ground with its cap at `top`, and the one
+    /// hanging above it with its cap at `top - gap`
+    fn pipe_pair(x: f32, top: f32, gap: f32) -> (Obb, Obb) {
+        let half = Vector2::new(26., 160.);
+        (
+            Obb::new(Point2::new(x, top + 160.), half, 0.),
+            Obb::new(Point2::new(x, top - gap - 160.), half, PI),
+        )
+    }
+
+    fn solid_mask(width: usize, height: usize) -> Mask {
+        Mask::from_rgba(width, height, &vec![255; width * height * 4])
+    }
+
+    #[test]
+    fn aabb_overlap_and_separation() {
+        assert!(aabb(0., 0., 10., 10.).intersects(&aabb(15., 5., 10., 10.)));
+        assert!(!aabb(0., 0., 10., 10.).intersects(&aabb(25., 0., 10., 10.)));
+        assert!(!aabb(0., 0., 10., 10.).intersects(&aabb(0., -21., 10., 10.)));
+    }
+
+    #[test]
+    fn aabb_grazing_edge_is_a_hit() {
+        assert!(aabb(0., 0., 10., 10.).intersects(&aabb(20., 0., 10., 10.)));
+        assert!(!aabb(0., 0., 10., 10.).intersects(&aabb(20.01, 0., 10., 10.)));
+    }
+
+    #[test]
+    fn obb_rotation_clears_corner() {
+        // the unrotated boxes overlap at the corners, tilting one 45
+        // degrees pulls its corner back in
+        let a = Obb::new(Point2::new(0., 0.), Vector2::new(10., 10.), 0.);
+        let b = Obb::new(Point2::new(21., 21.), Vector2::new(12., 12.), 0.);
+        assert!(a.intersects(&b));
+        let tilted = Obb {
+            angle: FRAC_PI_4,
+            ..b
+        };
+        assert!(!a.intersects(&tilted));
+        // but the axis aligned bounds of the tilted box still overlap
+        assert!(a.aabb().intersects(&tilted.aabb()));
+    }
+
+    #[test]
+    fn obb_rotation_reaches_past_box() {
+        // a long thin box tilted down reaches a box it misses when level
+        let stick = Obb::new(Point2::new(0., 0.), Vector2::new(30., 2.), 0.);
+        let target = Obb::new(Point2::new(20., 14.), Vector2::new(4., 4.), 0.);
+        assert!(!stick.intersects(&target));
+        assert!(Obb {
+            angle: 0.6,
+            ..stick
+        }
+        .intersects(&target));
+    }
+
+    #[test]
+    fn passing_through_the_gap() {
+        let (btm, top) = pipe_pair(100., 300., 80.);
+        // bird in the middle of the opening, level and tilted
+        for &angle in &[-0.45, 0., 1.5] {
+            let bird = Shape::Obb(Obb::new(
+                Point2::new(100., 260.),
+                Vector2::new(14., 12.),
+                angle,
+            ));
+            assert!(!bird.intersects(&Shape::Obb(btm)), "angle {}", angle);
+            assert!(!bird.intersects(&Shape::Obb(top)), "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn grazing_the_pipe_caps() {
+        let (btm, top) = pipe_pair(100., 300., 80.);
+        let half = Vector2::new(14., 12.);
+        // bottom edge of the bird exactly on the bottom pipe's cap
+        let low = Shape::Obb(Obb::new(Point2::new(100., 288.), half, 0.));
+        assert!(low.intersects(&Shape::Obb(btm)));
+        // top edge of the bird exactly on the top pipe's cap
+        let high = Shape::Obb(Obb::new(Point2::new(100., 232.), half, 0.));
+        assert!(high.intersects(&Shape::Obb(top)));
+        // a hair further in the opening misses both
+        let clear = Shape::Obb(Obb::new(Point2::new(100., 287.9), half, 0.));
+        assert!(!clear.intersects(&Shape::Obb(btm)));
+        // nose just touching the side of the pipe
+        let side = Shape::Obb(Obb::new(Point2::new(60., 400.), half, 0.));
+        assert!(side.intersects(&Shape::Obb(btm)));
+    }
+
+    #[test]
+    fn tilted_bird_grazes_cap_a_level_one_misses() {
+        let (btm, _) = pipe_pair(100., 300., 80.);
+        let half = Vector2::new(14., 12.);
+        let level = Obb::new(Point2::new(100., 285.), half, 0.);
+        assert!(!level.intersects(&btm));
+        // nosediving, the corners swing down into the cap
+        assert!(Obb {
+            angle: 0.5,
+            ..level
+        }
+        .intersects(&btm));
+    }
+
+    #[test]
+    fn mask_ignores_transparent_pixels() {
+        // only the left half of the sprite is solid
+        let mut rgba = vec![0; 8 * 8 * 4];
+        for y in 0..8 {
+            for x in 0..4 {
+                rgba[(y * 8 + x) * 4 + 3] = 255;
+            }
+        }
+        let mask = Mask::from_rgba(8, 8, &rgba);
+        let placed = Obb::new(Point2::new(0., 0.), mask.half_size(), 0.);
+        let sprite = Shape::Mask(placed, &mask);
+
+        let right = Shape::Aabb(aabb(3., 0., 0.5, 0.5));
+        let left = Shape::Aabb(aabb(-3., 0., 0.5, 0.5));
+        assert!(!right.intersects(&sprite));
+        assert!(left.intersects(&sprite));
+        // the boxes alone would have called it a hit
+        assert!(right.intersects(&Shape::Obb(placed)));
+
+        // flipped, the solid half is on the right
+        let flipped = Shape::Mask(
+            Obb {
+                angle: PI,
+                ..placed
+            },
+            &mask,
+        );
+        assert!(right.intersects(&flipped));
+        assert!(!left.intersects(&flipped));
+    }
+
+    #[test]
+    fn mask_repeats_last_row() {
+        let mut mask = solid_mask(4, 4);
+        let column = Obb::new(Point2::new(0., 8.), Vector2::new(2., 10.), 0.);
+        let below = Shape::Aabb(aabb(0., 15., 0.5, 0.5));
+        assert!(!below.intersects(&Shape::Mask(column, &mask)));
+        mask.repeat_last_row = true;
+        assert!(below.intersects(&Shape::Mask(column, &mask)));
+    }
+
+    #[test]
+    fn mask_against_mask() {
+        let mask = solid_mask(4, 4);
+        let a = Shape::Mask(Obb::new(Point2::new(0., 0.), mask.half_size(), 0.), &mask);
+        let near = Shape::Mask(Obb::new(Point2::new(3.5, 0.), mask.half_size(), 0.), &mask);
+        let far = Shape::Mask(Obb::new(Point2::new(4.5, 0.), mask.half_size(), 0.), &mask);
+        assert!(a.intersects(&near));
+        assert!(!a.intersects(&far));
+    }
+}
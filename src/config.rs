@@ -0,0 +1,442 @@
+use crate::{
+    actors::{Apple, FlapModel, Pipe, PipeSpawner, Player},
+    collision::ShapeKind,
+    error::{GameError, GameResult},
+    levels::Levels,
+    powerups::PowerUp,
+    world::{Ceiling, Dimensions},
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How the bird moves. Speeds are per tick, the same units as the consts
+/// they default to.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Physics {
+    pub fall_speed: f32,
+    pub flap_speed: f32,
+    /// seconds before the bird can flap again
+    pub flap_timeout: f32,
+    pub move_speed: f32,
+    pub up_angle_max: f32,
+    pub down_angle_max: f32,
+    pub flap_model: FlapModel,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics {
+            fall_speed: crate::FALL_SPEED,
+            flap_speed: crate::FLAP_SPEED,
+            flap_timeout: crate::FLAP_TIMEOUT,
+            move_speed: crate::MOVE_SPEED,
+            up_angle_max: Player::UP_ANGLE_MAX,
+            down_angle_max: Player::DOWN_ANGLE_MAX,
+            flap_model: FlapModel::Classic,
+        }
+    }
+}
+
+/// Where pipes get placed, before the level curve is applied
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipeConfig {
+    /// half the height of the opening
+    pub gap: f32,
+    pub between: f32,
+    pub first_x: f32,
+    /// closest an opening gets to the top of the screen or the ground,
+    /// before the level's margin multiplier
+    pub min_range: f32,
+    /// chance of an apple in each gap, from 0 to 1
+    pub apple_chance: f32,
+    /// chance of a power-up in each gap that didn't get an apple
+    pub powerup_chance: f32,
+}
+
+impl Default for PipeConfig {
+    fn default() -> Self {
+        PipeConfig {
+            gap: Pipe::PIPE_GAP,
+            between: Pipe::BETWEEN_PIPE,
+            first_x: Pipe::FIRST_PIPE_X,
+            min_range: Pipe::MIN_RANGE,
+            apple_chance: Apple::APPLE_CHANCE,
+            powerup_chance: PowerUp::POWERUP_CHANCE,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreenConfig {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        ScreenConfig {
+            width: crate::SCREEN_WIDTH,
+            height: crate::SCREEN_HEIGHT,
+        }
+    }
+}
+
+/// Collision test used for each kind of actor
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollisionConfig {
+    pub player: ShapeKind,
+    pub pipes: ShapeKind,
+}
+
+impl Default for CollisionConfig {
+    fn default() -> Self {
+        CollisionConfig {
+            player: ShapeKind::Obb,
+            pipes: ShapeKind::Aabb,
+        }
+    }
+}
+
+/// What the edges of the world do
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoundsConfig {
+    pub ceiling: Ceiling,
+}
+
+impl Default for BoundsConfig {
+    fn default() -> Self {
+        BoundsConfig {
+            ceiling: Ceiling::Clamp,
+        }
+    }
+}
+
+/// Gameplay settings, loaded from a toml file with `--config`. Anything
+/// missing from the file keeps its default.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub physics: Physics,
+    pub pipes: PipeConfig,
+    pub screen: ScreenConfig,
+    pub collision: CollisionConfig,
+    pub bounds: BoundsConfig,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> GameResult<Config> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).map_err(|e| {
+            GameError::ConfigError(format!("could not read {}: {}", path.display(), e))
+        })?;
+        Config::from_toml(&s)
+            .map_err(|e| GameError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    /// parse and validate, errors are plain messages naming the bad settings
+    pub fn from_toml(s: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(s).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// settings that differ from `other`, as `name: old -> new`
+    pub fn diff(&self, other: &Config) -> Vec<String> {
+        fn leaves(prefix: &str, value: toml::Value, out: &mut Vec<(String, toml::Value)>) {
+            match value {
+                toml::Value::Table(table) => {
+                    for (k, v) in table {
+                        let name = if prefix.is_empty() {
+                            k
+                        } else {
+                            format!("{}.{}", prefix, k)
+                        };
+                        leaves(&name, v, out);
+                    }
+                }
+                v => out.push((prefix.to_string(), v)),
+            }
+        }
+        let flatten = |config: &Config| {
+            let mut out = Vec::new();
+            if let Ok(value) = toml::Value::try_from(config) {
+                leaves("", value, &mut out);
+            }
+            out
+        };
+
+        flatten(self)
+            .into_iter()
+            .zip(flatten(other))
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((name, old), (_, new))| format!("{}: {} -> {}", name, old, new))
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, name: &str, value: f32, rule: &str| {
+            if !ok || !value.is_finite() {
+                errors.push(format!("{} must be {}, got {}", name, rule, value));
+            }
+        };
+
+        let p = &self.physics;
+        check(
+            p.fall_speed > 0.,
+            "physics.fall_speed",
+            p.fall_speed,
+            "positive",
+        );
+        check(
+            p.flap_speed > 0.,
+            "physics.flap_speed",
+            p.flap_speed,
+            "positive",
+        );
+        check(
+            p.flap_timeout >= 0.,
+            "physics.flap_timeout",
+            p.flap_timeout,
+            "zero or more",
+        );
+        check(
+            p.move_speed > 0.,
+            "physics.move_speed",
+            p.move_speed,
+            "positive",
+        );
+        check(
+            p.up_angle_max < p.down_angle_max,
+            "physics.up_angle_max",
+            p.up_angle_max,
+            "less than physics.down_angle_max",
+        );
+
+        let s = &self.screen;
+        check(s.width >= 100., "screen.width", s.width, "at least 100");
+        // the ground sprite goes below the ground line of the bundled
+        // background, which is as far as openings go
+        let ground = Dimensions::default().ground;
+        let floor = ground + crate::BASE_HEIGHT;
+        check(
+            s.height >= floor,
+            "screen.height",
+            s.height,
+            &format!("at least the ground and the ground sprite ({})", floor),
+        );
+
+        let pipes = &self.pipes;
+        check(pipes.gap > 0., "pipes.gap", pipes.gap, "positive");
+        let min_between = PipeSpawner::min_between();
+        check(
+            pipes.between > min_between,
+            "pipes.between",
+            pipes.between,
+            &format!("more than the bird needs to clear a pipe ({})", min_between),
+        );
+        check(
+            pipes.first_x >= 0.,
+            "pipes.first_x",
+            pipes.first_x,
+            "zero or more",
+        );
+        // openings are placed between the top of the screen and the ground
+        check(
+            pipes.min_range >= 0. && pipes.min_range < ground / 2.,
+            "pipes.min_range",
+            pipes.min_range,
+            &format!("between 0 and half the ground height ({})", ground / 2.),
+        );
+        check(
+            (0. ..=1.).contains(&pipes.apple_chance),
+            "pipes.apple_chance",
+            pipes.apple_chance,
+            "between 0 and 1",
+        );
+        check(
+            pipes.powerup_chance >= 0. && pipes.apple_chance + pipes.powerup_chance <= 1.,
+            "pipes.powerup_chance",
+            pipes.powerup_chance,
+            "between 0 and 1 - pipes.apple_chance",
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Check the settings still leave room with `levels` applied: the
+    /// level with the widest margin has to leave openings somewhere to go,
+    /// and the one with the tightest spacing room between the pairs.
+    pub fn validate_levels(&self, levels: &Levels) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let ground = Dimensions::default().ground;
+        let margin = self.pipes.min_range * levels.max_margin();
+        if margin >= ground / 2. {
+            errors.push(format!(
+                "pipes.min_range {} times the largest level margin {} leaves no room for \
+                 openings, it must come to less than {}",
+                self.pipes.min_range,
+                levels.max_margin(),
+                ground / 2.
+            ));
+        }
+        let min_between = PipeSpawner::min_between();
+        if self.pipes.between * levels.min_spacing() <= min_between {
+            errors.push(format!(
+                "pipes.between {} times the smallest level spacing {} leaves no room \
+                 between pipes, it must come to more than {}",
+                self.pipes.between,
+                levels.min_spacing(),
+                min_between
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+/// Polls a config file for changes so settings can be tuned while the game
+/// is running
+#[derive(Debug, Clone)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl ConfigWatcher {
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        ConfigWatcher {
+            modified: modified(&path),
+            path,
+            last_check: Instant::now(),
+        }
+    }
+
+    /// the newly loaded config if the file changed since the last poll
+    pub fn poll(&mut self) -> Option<GameResult<Config>> {
+        if self.last_check.elapsed() < ConfigWatcher::INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Config::load(&self.path))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejects(s: &str, name: &str) {
+        match Config::from_toml(s) {
+            Ok(_) => panic!("{:?} should not load", s),
+            Err(e) => assert!(e.contains(name), "{:?} doesn't mention {}", e, name),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(Config::from_toml(""), Ok(Config::default()));
+        let config = Config::default();
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.validate_levels(&Levels::default()), Ok(()));
+    }
+
+    #[test]
+    fn bad_settings_are_named() {
+        rejects("[physics]\nfall_speed = 0.0", "physics.fall_speed");
+        rejects("[physics]\nflap_timeout = -1.0", "physics.flap_timeout");
+        rejects("[physics]\nup_angle_max = 2.0", "physics.up_angle_max");
+        rejects("[screen]\nwidth = 50.0", "screen.width");
+        rejects("[pipes]\ngap = nan", "pipes.gap");
+        rejects(
+            "[pipes]\napple_chance = 0.8\npowerup_chance = 0.5",
+            "pipes.powerup_chance",
+        );
+        rejects("[pipes]\nwiggle = 1.0", "wiggle");
+    }
+
+    #[test]
+    fn openings_need_room_above_the_ground() {
+        rejects("[pipes]\nmin_range = 300.0", "pipes.min_range");
+        rejects("[pipes]\nmin_range = -1.0", "pipes.min_range");
+
+        // fine on its own, but not once a level widens the margin by half
+        let levels = Levels::from_toml(
+            "[[level]]\nscore = 0\ngap = 1.0\nspacing = 1.0\nspeed = 1.0\nmargin = 1.5\n",
+        )
+        .unwrap();
+        let config = Config::from_toml("[pipes]\nmin_range = 200.0").unwrap();
+        assert_eq!(config.validate_levels(&Levels::default()), Ok(()));
+        assert!(config.validate_levels(&levels).is_err());
+        let config = Config::from_toml("[pipes]\nmin_range = 170.0").unwrap();
+        assert_eq!(config.validate_levels(&levels), Ok(()));
+    }
+
+    #[test]
+    fn the_ground_fits_on_screen() {
+        rejects("[screen]\nheight = 600.0", "screen.height");
+        let floor = Dimensions::default().ground + crate::BASE_HEIGHT;
+        let config = Config::from_toml(&format!("[screen]\nheight = {:?}", floor));
+        assert!(config.is_ok());
+    }
+
+    #[test]
+    fn pipes_leave_room_between_them() {
+        rejects("[pipes]\nbetween = 0.5", "pipes.between");
+        rejects("[pipes]\nbetween = 52.0", "pipes.between");
+        let config = Config::from_toml("[pipes]\nbetween = 120.0").unwrap();
+        assert!(config.pipes.between > PipeSpawner::min_between());
+
+        // fine on its own, but not once a level packs the pairs tighter
+        let levels = Levels::from_toml(
+            "[[level]]\nscore = 0\ngap = 1.0\nspacing = 0.5\nspeed = 1.0\nmargin = 1.0\n",
+        )
+        .unwrap();
+        let config = Config::from_toml("[pipes]\nbetween = 150.0").unwrap();
+        assert_eq!(config.validate_levels(&Levels::default()), Ok(()));
+        let e = config.validate_levels(&levels).unwrap_err();
+        assert!(e.contains("pipes.between"), "{}", e);
+    }
+
+    #[test]
+    fn diff_names_what_changed() {
+        let old = Config::default();
+        let new = Config::from_toml("[physics]\nfall_speed = 1.0").unwrap();
+        assert_eq!(old.diff(&old), Vec::<String>::new());
+        let changes = new.diff(&old);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].starts_with("physics.fall_speed: 1.0 -> "));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    env::Observation,
+    validate::validate,
+    world::{InputState, World},
+};
+
+use std::{collections::VecDeque, fmt};
+
+/// Decides the input for each tick in place of a player
+pub trait Controller: fmt::Debug {
+    /// the input to step `world` with next
+    fn input(&mut self, world: &World) -> InputState;
+}
+
+/// Flies at the centre of the upcoming gap. A flap carries the bird up by a
+/// fixed height before it falls again, so flapping as it falls through half
+/// that height below the centre keeps it bouncing evenly around it.
+///
+/// Gaps can be barely taller than that bounce, and a way into one can
+/// leave no way out towards the next, so the autopilot plans its flaps
+/// with `validate` through the next few pairs, its aim tried first at
+/// every tick. It follows the plan past the first of them and plans again
+/// from there.
+#[derive(Debug, Default, Clone)]
+pub struct Autopilot {
+    plan: VecDeque<bool>,
+    /// the tick the plan's next flap is for
+    tick: u64,
+    /// pipes passed when it's time to plan again
+    replan_at: usize,
+}
+
+impl Autopilot {
+    /// pairs planned through at a time
+    const PAIRS: usize = 4;
+    /// most ticks played to make one plan, in case there's no way through
+    const BUDGET: u64 = 200_000;
+
+    fn dt() -> f32 {
+        1. / crate::DESIRED_FPS as f32
+    }
+
+    /// how far a flap from a standstill lifts the bird, in pixels
+    fn climb(world: &World) -> f32 {
+        let physics = world.player.physics;
+        let dt = Autopilot::dt();
+        // speed and gravity per tick, the way `Player` applies them
+        let speed = physics.flap_speed * dt;
+        let gravity = physics.fall_speed * dt;
+        speed * speed / (2. * gravity)
+    }
+
+    /// whether to flap to stay around the centre of the gap
+    pub(crate) fn aim(world: &World) -> bool {
+        let obs = Observation::of(world);
+        let centre = (obs.gap_top + obs.gap_bottom) / 2.;
+        // where the bird will be by the time a flap takes effect
+        let next_y = obs.player_y + obs.player_velocity;
+        obs.player_velocity >= 0. && next_y > centre + Autopilot::climb(world) / 2.
+    }
+
+    /// Plan from `world` once the first pair of the last plan is behind
+    /// it. A world the plan wasn't made for, like a new run, is planned
+    /// for afresh.
+    fn plan(&mut self, world: &World) {
+        let stale = world.ticks != self.tick;
+        if stale {
+            self.plan.clear();
+        } else if world.passed < self.replan_at && !self.plan.is_empty() {
+            return;
+        }
+        // the search skips places near enough ones it's been stuck in, so it
+        // can miss a way through that the last plan still has
+        if let Ok(path) = validate(world, Autopilot::PAIRS, Autopilot::BUDGET) {
+            self.plan = path.into();
+        }
+        self.tick = world.ticks;
+        self.replan_at = world.passed + 1;
+    }
+}
+
+impl Controller for Autopilot {
+    fn input(&mut self, world: &World) -> InputState {
+        if !world.state.is_playing() {
+            return InputState {
+                flap: Autopilot::aim(world),
+            };
+        }
+        self.plan(world);
+        self.tick += 1;
+        InputState {
+            flap: self
+                .plan
+                .pop_front()
+                .unwrap_or_else(|| Autopilot::aim(world)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::Env, seed::GameSeed};
+
+    use std::ops::Range;
+
+    // fly the autopilot from the start of each course until it's passed
+    // `pipes` pairs, one autopilot for every run like the game restarting
+    fn clears(seeds: Range<u64>, pipes: usize) {
+        let mut env = Env::default();
+        let mut autopilot = Autopilot::default();
+        for seed in seeds {
+            env.reset(GameSeed(seed));
+            while env.world().passed < pipes {
+                let input = autopilot.input(env.world());
+                let (_, _, done, info) = env.step(input);
+                assert!(!done, "seed {}: crashed with score {}", seed, info.score);
+            }
+        }
+    }
+
+    #[test]
+    fn clears_the_first_pipes() {
+        clears(0..3, 10);
+    }
+
+    #[test]
+    #[ignore = "slow, run with cargo test --release -- --ignored"]
+    fn clears_many_courses() {
+        clears(0..20, 30);
+    }
+
+    #[test]
+    fn plans_again_for_a_new_run() {
+        let mut env = Env::default();
+        env.reset(GameSeed(1));
+        let mut autopilot = Autopilot::default();
+        for _ in 0..100 {
+            let input = autopilot.input(env.world());
+            env.step(input);
+        }
+        // the old plan was for another course
+        env.reset(GameSeed(2));
+        let world = env.world().clone();
+        autopilot.input(&world);
+        let fresh = validate(&world, Autopilot::PAIRS, Autopilot::BUDGET).unwrap();
+        assert!(autopilot.plan.iter().eq(&fresh[1..]));
+    }
+}
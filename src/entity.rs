@@ -0,0 +1,276 @@
+use crate::{
+    actors::{Actor, Apple, AppleKind, Pipe},
+    collision::{Masks, Shape},
+    config::CollisionConfig,
+    powerups::{PowerUp, PowerUpKind},
+};
+
+use nalgebra::{Point2, Vector2};
+
+/// What an actor is drawn with, `Assets` has the image for each
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Sprite {
+    Bird,
+    Pipe,
+    Apple(AppleKind),
+    /// drawn as a coloured badge, there's no image for these
+    PowerUp(PowerUpKind),
+}
+
+/// What collision shapes get built from
+#[derive(Debug, Copy, Clone)]
+pub struct ShapeParams<'a> {
+    pub kinds: CollisionConfig,
+    /// sprite masks for `ShapeKind::Mask`, if the sprites are loaded
+    pub masks: Option<&'a Masks>,
+    /// how far pipes run on past the end of their sprite
+    pub reach: f32,
+}
+
+/// What the bird touching an entity does
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Contact {
+    /// a crash
+    Solid,
+    Apple(AppleKind),
+    PowerUp(PowerUpKind),
+}
+
+/// Something that's only there to look at, gone after `ttl` ticks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoration {
+    pub pos: Point2<f32>,
+    /// pixels per tick
+    pub velocity: Vector2<f32>,
+    pub sprite: Sprite,
+    pub bbox_size: Point2<f32>,
+    pub ttl: u32,
+    life: u32,
+}
+
+impl Decoration {
+    const FLOAT_TICKS: u32 = 30;
+
+    /// a collected pickup drifting up and fading out
+    pub fn floating(sprite: Sprite, pos: Point2<f32>, bbox_size: Point2<f32>) -> Self {
+        Decoration {
+            pos,
+            velocity: Vector2::new(0., -1.5),
+            sprite,
+            bbox_size,
+            ttl: Decoration::FLOAT_TICKS,
+            life: Decoration::FLOAT_TICKS,
+        }
+    }
+}
+
+impl Actor for Decoration {
+    fn pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    fn velocity(&self) -> Vector2<f32> {
+        self.velocity
+    }
+
+    fn bbox_size(&self) -> Point2<f32> {
+        self.bbox_size
+    }
+
+    fn sprite(&self) -> Sprite {
+        self.sprite
+    }
+
+    fn alpha(&self) -> f32 {
+        self.ttl as f32 / self.life as f32
+    }
+
+    fn shape<'a>(&self, _params: ShapeParams<'a>) -> Option<Shape<'a>> {
+        None
+    }
+
+    fn update(&mut self, _dt: f32) {
+        self.pos += self.velocity;
+        self.ttl = self.ttl.saturating_sub(1);
+    }
+
+    fn despawned(&self, offset: f32) -> bool {
+        self.ttl == 0 || self.pos.x + self.bbox_size.x + offset <= 0.
+    }
+}
+
+/// Everything on the course besides the player. The bird shares `Actor`
+/// with these but stays out of the list: it's in world space rather than
+/// scrolling with the course, input moves it, and each world has exactly
+/// one, so `World::player` holds it and it's drawn on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entity {
+    Pipe(Pipe),
+    Apple(Apple),
+    PowerUp(PowerUp),
+    Decoration(Decoration),
+}
+
+// run `$body` with `$actor` bound to whichever actor the entity holds
+macro_rules! each {
+    ($entity:expr, $actor:ident => $body:expr) => {
+        match $entity {
+            Entity::Pipe($actor) => $body,
+            Entity::Apple($actor) => $body,
+            Entity::PowerUp($actor) => $body,
+            Entity::Decoration($actor) => $body,
+        }
+    };
+}
+
+impl Entity {
+    pub fn contact(&self) -> Option<Contact> {
+        match self {
+            Entity::Pipe(_) => Some(Contact::Solid),
+            Entity::Apple(apple) => Some(Contact::Apple(apple.kind)),
+            Entity::PowerUp(powerup) => Some(Contact::PowerUp(powerup.kind)),
+            Entity::Decoration(_) => None,
+        }
+    }
+
+    pub fn as_pipe(&self) -> Option<&Pipe> {
+        match self {
+            Entity::Pipe(pipe) => Some(pipe),
+            _ => None,
+        }
+    }
+}
+
+impl Actor for Entity {
+    fn pos(&self) -> Point2<f32> {
+        each!(self, a => a.pos())
+    }
+
+    fn velocity(&self) -> Vector2<f32> {
+        each!(self, a => a.velocity())
+    }
+
+    fn facing(&self) -> f32 {
+        each!(self, a => a.facing())
+    }
+
+    fn bbox_size(&self) -> Point2<f32> {
+        each!(self, a => a.bbox_size())
+    }
+
+    fn sprite(&self) -> Sprite {
+        each!(self, a => a.sprite())
+    }
+
+    fn alpha(&self) -> f32 {
+        each!(self, a => a.alpha())
+    }
+
+    fn shape<'a>(&self, params: ShapeParams<'a>) -> Option<Shape<'a>> {
+        each!(self, a => a.shape(params))
+    }
+
+    fn update(&mut self, dt: f32) {
+        each!(self, a => a.update(dt))
+    }
+
+    fn despawned(&self, offset: f32) -> bool {
+        each!(self, a => a.despawned(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actors::PipeMotion;
+
+    fn params() -> ShapeParams<'static> {
+        ShapeParams {
+            kinds: CollisionConfig::default(),
+            masks: None,
+            reach: 0.,
+        }
+    }
+
+    fn apple() -> Apple {
+        Apple::new(Point2::new(40., 20.), AppleKind::Red)
+    }
+
+    fn powerup() -> PowerUp {
+        PowerUp {
+            pos: Point2::new(60., -20.),
+            kind: PowerUpKind::Ghost,
+            bbox_size: Point2::new(12., 12.),
+        }
+    }
+
+    #[test]
+    fn entities_do_what_their_actor_does() {
+        let motion = PipeMotion::Oscillate {
+            amplitude: 20.,
+            period: 60.,
+        };
+        let mut pipe = Pipe::at(Point2::new(100., 50.), 0., motion);
+        let mut entity = Entity::Pipe(pipe.clone());
+        assert_eq!(entity.pos(), pipe.pos());
+        assert_eq!(entity.bbox_size(), pipe.bbox_size());
+        assert_eq!(entity.sprite(), Sprite::Pipe);
+        // a moving pipe is moved through the entity
+        pipe.update(0.25);
+        entity.update(0.25);
+        assert_eq!(entity, Entity::Pipe(pipe));
+
+        let entity = Entity::Apple(apple());
+        assert_eq!(entity.pos(), apple().pos());
+        assert_eq!(entity.sprite(), Sprite::Apple(AppleKind::Red));
+        let entity = Entity::PowerUp(powerup());
+        assert_eq!(entity.sprite(), Sprite::PowerUp(PowerUpKind::Ghost));
+        assert_eq!(entity.alpha(), 1.);
+    }
+
+    #[test]
+    fn contact_says_what_touching_does() {
+        let pipe = Entity::Pipe(Pipe::new());
+        assert_eq!(pipe.contact(), Some(Contact::Solid));
+        assert!(pipe.as_pipe().is_some());
+        let apple = Entity::Apple(apple());
+        assert_eq!(apple.contact(), Some(Contact::Apple(AppleKind::Red)));
+        assert!(apple.as_pipe().is_none());
+        let powerup = Entity::PowerUp(powerup());
+        assert_eq!(
+            powerup.contact(),
+            Some(Contact::PowerUp(PowerUpKind::Ghost))
+        );
+
+        // the ones with a contact have something to touch
+        for entity in &[pipe, apple, powerup] {
+            assert!(entity.shape(params()).is_some());
+        }
+    }
+
+    #[test]
+    fn eaten_pickups_float_off_and_fade() {
+        let eaten = apple();
+        let mut entity = Entity::Decoration(Decoration::floating(
+            eaten.sprite(),
+            eaten.pos,
+            eaten.bbox_size,
+        ));
+        assert_eq!(entity.contact(), None);
+        assert!(entity.shape(params()).is_none());
+        assert_eq!(entity.alpha(), 1.);
+
+        let mut ticks = 0;
+        let mut alpha = entity.alpha();
+        while !entity.despawned(0.) {
+            entity.update(1. / 60.);
+            ticks += 1;
+            assert!(entity.pos().y < eaten.pos.y);
+            assert!(entity.alpha() < alpha);
+            alpha = entity.alpha();
+        }
+        assert_eq!(ticks, Decoration::FLOAT_TICKS);
+        assert_eq!(alpha, 0.);
+        assert_eq!(entity.pos().x, eaten.pos.x);
+    }
+}
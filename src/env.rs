@@ -0,0 +1,256 @@
+use crate::{
+    config::Config,
+    gamestate::GameState,
+    levels::Levels,
+    seed::GameSeed,
+    world::{Dimensions, Event, InputState, World},
+};
+
+/// What an agent sees of the world after each step. Everything is in screen
+/// pixels with y pointing down, like the window.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Observation {
+    /// centre of the bird
+    pub player_y: f32,
+    /// pixels per tick, positive is falling
+    pub player_velocity: f32,
+    /// from the bird to the middle of the next pipe pair
+    pub pipe_distance: f32,
+    /// the mouth of the top pipe
+    pub gap_top: f32,
+    /// the mouth of the bottom pipe
+    pub gap_bottom: f32,
+}
+
+impl Observation {
+    pub const SIZE: usize = 5;
+
+    pub fn of(world: &World) -> Self {
+        let player = world.player_pos();
+        let (pipe_distance, gap_top, gap_bottom) = match world.next_pair() {
+            Some((bottom, top)) => (
+                bottom.pos.x - player.x,
+                top.pos.y + top.bbox_size.y,
+                bottom.pos.y - bottom.bbox_size.y,
+            ),
+            // the spawner keeps pipes ahead, but an open sky is a fine answer
+            None => (world.dims.screen_width, 0., world.dims.ground),
+        };
+        Observation {
+            player_y: player.y,
+            player_velocity: -world.player.velocity.y,
+            pipe_distance,
+            gap_top,
+            gap_bottom,
+        }
+    }
+
+    /// the fields in order, for feeding to a model
+    pub fn to_array(self) -> [f32; Observation::SIZE] {
+        [
+            self.player_y,
+            self.player_velocity,
+            self.pipe_distance,
+            self.gap_top,
+            self.gap_bottom,
+        ]
+    }
+}
+
+/// Extra detail about a step, for logging rather than learning from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Info {
+    pub score: usize,
+    pub ticks: u64,
+    /// what happened during the step
+    pub events: Vec<Event>,
+}
+
+/// A gym style environment over the game rules. It never opens a window or
+/// loads a sprite, so agents can be trained on a headless server.
+///
+/// Each episode starts straight into play, skipping the menu and countdown,
+/// and ends when the bird crashes. A step is one fixed tick of the game.
+#[derive(Debug, Clone)]
+pub struct Env {
+    world: World,
+    levels: Levels,
+    config: Config,
+}
+
+impl Env {
+    /// reward for every tick survived
+    pub const ALIVE_REWARD: f32 = 0.1;
+    /// reward for each point scored, from pipes or apples
+    pub const POINT_REWARD: f32 = 1.;
+    /// reward for the step that ends the episode, in place of the alive
+    /// reward
+    pub const DEATH_REWARD: f32 = -1.;
+
+    pub fn new(levels: Levels, config: Config) -> Self {
+        let world = Env::world_for(GameSeed(0), &levels, config);
+        Env {
+            world,
+            levels,
+            config,
+        }
+    }
+
+    fn world_for(seed: GameSeed, levels: &Levels, config: Config) -> World {
+        let dims = Dimensions {
+            screen_width: config.screen.width,
+            screen_height: config.screen.height,
+            ..Dimensions::default()
+        };
+        let mut world = World::new(dims, seed, levels.clone(), config);
+        world.state = GameState::Playing;
+        world
+    }
+
+    /// start a new episode on the course for `seed`
+    pub fn reset(&mut self, seed: GameSeed) -> Observation {
+        self.world = Env::world_for(seed, &self.levels, self.config);
+        Observation::of(&self.world)
+    }
+
+    /// Play one tick with `input`, giving back what the agent sees after it,
+    /// the reward, whether the episode is over and some info. Stepping an
+    /// episode that's over does nothing.
+    pub fn step(&mut self, input: InputState) -> (Observation, f32, bool, Info) {
+        let done = self.world.state.is_gameover();
+        let reward = if done {
+            self.world.events.clear();
+            0.
+        } else {
+            let score = self.world.score;
+            self.world.step(1. / crate::DESIRED_FPS as f32, input);
+            // a pipe cleared on the tick the bird crashes still counts
+            let points = (self.world.score - score) as f32 * Env::POINT_REWARD;
+            if self.world.state.is_gameover() {
+                Env::DEATH_REWARD + points
+            } else {
+                Env::ALIVE_REWARD + points
+            }
+        };
+        let info = Info {
+            score: self.world.score,
+            ticks: self.world.ticks,
+            events: self.world.events.clone(),
+        };
+        let done = self.world.state.is_gameover();
+        (Observation::of(&self.world), reward, done, info)
+    }
+
+    /// the world being played, for anyone wanting more than the observation
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+}
+
+impl Default for Env {
+    /// the bundled levels and default settings
+    fn default() -> Self {
+        Env::new(Levels::default(), Config::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Entity;
+
+    const FLAP: InputState = InputState { flap: true };
+    const GLIDE: InputState = InputState { flap: false };
+
+    /// the observations from flapping every `every` ticks until the crash
+    fn episode(env: &mut Env, seed: GameSeed, every: usize) -> Vec<Observation> {
+        let mut seen = vec![env.reset(seed)];
+        for tick in 0.. {
+            let input = if tick % every == 0 { FLAP } else { GLIDE };
+            let (observation, _, done, _) = env.step(input);
+            seen.push(observation);
+            if done {
+                break;
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn resets_replay_the_same_episode() {
+        let mut env = Env::default();
+        let first = episode(&mut env, GameSeed(5), 25);
+        assert_eq!(episode(&mut env, GameSeed(5), 25), first);
+        assert_eq!(Env::default().reset(GameSeed(5)), first[0]);
+        assert_ne!(episode(&mut env, GameSeed(6), 25), first);
+    }
+
+    /// move the first pair behind the bird, out of its way, so the next
+    /// step counts it as passed
+    fn clear_the_first_pair(env: &mut Env) {
+        let x = env.world.player_pos().x - 100.;
+        for entity in env.world.entities.iter_mut().take(2) {
+            if let Entity::Pipe(pipe) = entity {
+                pipe.pos.x = x;
+            }
+        }
+    }
+
+    #[test]
+    fn rewards_for_staying_alive_and_scoring() {
+        let mut env = Env::default();
+        env.reset(GameSeed(3));
+        for _ in 0..10 {
+            let (_, reward, done, info) = env.step(FLAP);
+            assert!(!done);
+            assert_eq!(info.score, 0);
+            assert_eq!(reward, Env::ALIVE_REWARD);
+        }
+        clear_the_first_pair(&mut env);
+        let (_, reward, done, info) = env.step(GLIDE);
+        assert!(!done);
+        assert_eq!(info.score, 1);
+        assert_eq!(reward, Env::ALIVE_REWARD + Env::POINT_REWARD);
+    }
+
+    #[test]
+    fn a_crash_ends_the_episode_for_good() {
+        let mut env = Env::default();
+        env.reset(GameSeed(3));
+        let (mut reward, mut done, mut info) = (0., false, None);
+        while !done {
+            let step = env.step(GLIDE);
+            reward = step.1;
+            done = step.2;
+            info = Some(step.3);
+        }
+        let info = info.unwrap();
+        assert_eq!(reward, Env::DEATH_REWARD);
+        assert!(info.events.iter().any(|e| matches!(e, Event::Hit(_))));
+
+        // stepping on does nothing, the bird is down for good
+        let observation = Observation::of(env.world());
+        for _ in 0..10 {
+            let (after, reward, done, after_info) = env.step(FLAP);
+            assert!(done);
+            assert_eq!(reward, 0.);
+            assert_eq!(after_info.ticks, info.ticks);
+            assert_eq!(after_info.score, info.score);
+            assert!(after_info.events.is_empty());
+            assert_eq!(after.player_velocity, observation.player_velocity);
+        }
+    }
+
+    #[test]
+    fn points_on_the_crash_tick_still_count() {
+        let mut env = Env::default();
+        env.reset(GameSeed(3));
+        // the bird about to hit the ground just as it clears the first pair
+        clear_the_first_pair(&mut env);
+        env.world.player.pos.y = -env.world.dims.screen_height;
+        let (_, reward, done, info) = env.step(GLIDE);
+        assert!(done);
+        assert_eq!(info.score, 1);
+        assert_eq!(reward, Env::DEATH_REWARD + Env::POINT_REWARD);
+    }
+}
@@ -0,0 +1,45 @@
+//! Errors from loading settings and saved files. In the windowed game these
+//! are ggez's own, so they `?` straight into its results; built without a
+//! window, a stand-in with the variants the rules use takes their place.
+
+#[cfg(feature = "window")]
+pub use ggez::{GameError, GameResult};
+
+#[cfg(not(feature = "window"))]
+pub use self::headless::{GameError, GameResult};
+
+#[cfg(not(feature = "window"))]
+mod headless {
+    use std::{error::Error, fmt, io, sync::Arc};
+
+    #[derive(Debug, Clone)]
+    pub enum GameError {
+        ConfigError(String),
+        IOError(Arc<io::Error>),
+    }
+
+    pub type GameResult<T = ()> = Result<T, GameError>;
+
+    impl fmt::Display for GameError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                GameError::ConfigError(s) => write!(f, "Config error: {}", s),
+                GameError::IOError(e) => write!(f, "IO error: {}", e),
+            }
+        }
+    }
+
+    impl Error for GameError {}
+
+    impl From<io::Error> for GameError {
+        fn from(e: io::Error) -> GameError {
+            GameError::IOError(Arc::new(e))
+        }
+    }
+
+    impl From<toml::de::Error> for GameError {
+        fn from(e: toml::de::Error) -> GameError {
+            GameError::ConfigError(format!("TOML decode error: {}", e))
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use ggez::{
+    event::{
+        winit_event::{ElementState, Event, KeyboardInput, TouchPhase, WindowEvent},
+        Button, EventHandler, EventsLoop,
+    },
+    input::{keyboard, mouse},
+    Context, GameResult,
+};
+
+/// An `EventHandler` that also wants touchscreen events. Gamepad buttons
+/// come through here too, since only ggez can make a `GamepadId` for
+/// `EventHandler::gamepad_button_down_event`.
+pub trait InputHandler: EventHandler {
+    /// `x` and `y` are in window coordinates like the mouse events
+    fn touch_event(&mut self, _ctx: &mut Context, _phase: TouchPhase, _x: f32, _y: f32) {}
+
+    fn pad_button_event(&mut self, _ctx: &mut Context, _button: Button, _pressed: bool) {}
+}
+
+/// The main loop from `ggez::event::run`, which drops touch events, cut
+/// down to the events we use and with touches passed on.
+pub fn run<S>(ctx: &mut Context, events_loop: &mut EventsLoop, state: &mut S) -> GameResult
+where
+    S: InputHandler,
+{
+    while ctx.continuing {
+        ctx.timer_context.tick();
+        events_loop.poll_events(|event| {
+            let event = ctx.process_event(&event);
+            if let Event::WindowEvent { event, .. } = event {
+                match event {
+                    WindowEvent::CloseRequested if !state.quit_event(ctx) => ggez::quit(ctx),
+                    WindowEvent::Focused(gained) => state.focus_event(ctx, gained),
+                    WindowEvent::ReceivedCharacter(ch) => state.text_input_event(ctx, ch),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: key_state,
+                                virtual_keycode: Some(keycode),
+                                modifiers,
+                                ..
+                            },
+                        ..
+                    } => match key_state {
+                        ElementState::Pressed => {
+                            let repeat = keyboard::is_key_repeated(ctx);
+                            state.key_down_event(ctx, keycode, modifiers.into(), repeat);
+                        }
+                        ElementState::Released => {
+                            state.key_up_event(ctx, keycode, modifiers.into())
+                        }
+                    },
+                    WindowEvent::MouseInput {
+                        state: button_state,
+                        button,
+                        ..
+                    } => {
+                        let position = mouse::position(ctx);
+                        match button_state {
+                            ElementState::Pressed => {
+                                state.mouse_button_down_event(ctx, button, position.x, position.y)
+                            }
+                            ElementState::Released => {
+                                state.mouse_button_up_event(ctx, button, position.x, position.y)
+                            }
+                        }
+                    }
+                    WindowEvent::Touch(touch) => {
+                        let (x, y) = (touch.location.x as f32, touch.location.y as f32);
+                        state.touch_event(ctx, touch.phase, x, y);
+                    }
+                    _ => (),
+                }
+            }
+        });
+        if ctx.conf.modules.gamepad {
+            while let Some(gilrs::Event { event, .. }) = ctx.gamepad_context.next_event() {
+                match event {
+                    gilrs::EventType::ButtonPressed(button, _) => {
+                        state.pad_button_event(ctx, button, true);
+                    }
+                    gilrs::EventType::ButtonReleased(button, _) => {
+                        state.pad_button_event(ctx, button, false);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        state.update(ctx)?;
+        state.draw(ctx)?;
+    }
+
+    Ok(())
+}
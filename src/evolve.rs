@@ -0,0 +1,324 @@
+use crate::{brain::Brain, controller::Controller, env::Env, seed::GameSeed};
+
+use rand::{rngs::StdRng, Rng};
+
+use std::{cmp::Ordering, thread};
+
+/// How far a brain got over its courses
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Fitness {
+    /// pipe spacings flown
+    pub distance: f32,
+    pub score: usize,
+}
+
+impl Fitness {
+    /// Distance plus score, both counted in pipes. Flying further always
+    /// helps, even short of the next pipe, and each point is worth as much
+    /// as another pipe flown.
+    pub fn total(self) -> f32 {
+        self.distance + self.score as f32
+    }
+}
+
+/// Fly `brain` over the course for each of `courses` without a window,
+/// cutting each run off after `max_ticks` in case it never crashes.
+pub fn evaluate(brain: &Brain, courses: &[GameSeed], max_ticks: u64) -> Fitness {
+    let mut env = Env::default();
+    let mut brain = brain.clone();
+    let mut fitness = Fitness::default();
+    for &seed in courses {
+        env.reset(seed);
+        loop {
+            let input = brain.input(env.world());
+            let (_, _, done, info) = env.step(input);
+            if done || info.ticks >= max_ticks {
+                break;
+            }
+        }
+        let world = env.world();
+        fitness.distance += -world.offset / world.config.pipes.between;
+        fitness.score += world.score;
+    }
+    fitness
+}
+
+/// How a training run goes
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Settings {
+    /// brains in each generation
+    pub population: usize,
+    /// courses each brain flies a generation
+    pub courses: usize,
+    /// longest a run goes on, in ticks
+    pub max_ticks: u64,
+    /// threads the brains are flown on
+    pub threads: usize,
+    /// the fittest brains, carried over to the next generation as they are
+    pub elite: usize,
+    /// chance of each weight of a child being nudged
+    pub mutation_rate: f32,
+    /// most a weight is nudged by
+    pub mutation_strength: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            population: 100,
+            courses: 3,
+            max_ticks: 120 * crate::DESIRED_FPS as u64,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            elite: 5,
+            mutation_rate: 0.1,
+            mutation_strength: 0.5,
+        }
+    }
+}
+
+impl Settings {
+    /// whether a run can go with these, naming the first bad one if not
+    pub fn check(&self) -> Result<(), String> {
+        if self.population == 0 {
+            return Err("population must be at least 1".to_string());
+        }
+        if self.courses == 0 {
+            return Err("courses must be at least 1".to_string());
+        }
+        if self.max_ticks == 0 {
+            return Err("max ticks must be at least 1".to_string());
+        }
+        if self.threads == 0 {
+            return Err("threads must be at least 1".to_string());
+        }
+        if self.elite > self.population {
+            return Err(format!(
+                "elite must be at most the population ({}), got {}",
+                self.population, self.elite
+            ));
+        }
+        if !(0. ..=1.).contains(&self.mutation_rate) {
+            return Err(format!(
+                "mutation rate must be between 0 and 1, got {}",
+                self.mutation_rate
+            ));
+        }
+        if !(self.mutation_strength > 0. && self.mutation_strength.is_finite()) {
+            return Err(format!(
+                "mutation strength must be positive, got {}",
+                self.mutation_strength
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A generation of brains, each bred from the fittest of the one before
+#[derive(Debug, Clone)]
+pub struct Population {
+    pub brains: Vec<Brain>,
+    pub generation: usize,
+    pub settings: Settings,
+    // courses and breeding both follow from this, so a run can be repeated
+    seed: GameSeed,
+    rng: StdRng,
+}
+
+impl Population {
+    /// brains picked from to breed each child, the fittest wins
+    const TOURNAMENT: usize = 3;
+    /// courses the champions are compared on
+    pub const HELD_OUT: usize = 10;
+
+    /// a first generation of random brains
+    pub fn new(settings: Settings, seed: GameSeed) -> Self {
+        let mut rng = seed.world_rng();
+        let brains = (0..settings.population.max(1))
+            .map(|_| Brain::random(&mut rng))
+            .collect();
+        Population {
+            brains,
+            generation: 0,
+            settings,
+            seed,
+            rng,
+        }
+    }
+
+    /// the courses this generation flies, new ones each generation so the
+    /// brains can't learn one course by heart
+    pub fn courses(&self) -> Vec<GameSeed> {
+        let first = self.generation * self.settings.courses;
+        (first..first + self.settings.courses)
+            .map(|i| GameSeed(self.seed.0.wrapping_add(i as u64)))
+            .collect()
+    }
+
+    /// Courses no generation trains on, the same for the whole run, for
+    /// telling whether one generation's champion beats another's. Training
+    /// counts up from the seed, these count down from just below it.
+    pub fn held_out(&self) -> Vec<GameSeed> {
+        (1..=Population::HELD_OUT)
+            .map(|i| GameSeed(self.seed.0.wrapping_sub(i as u64)))
+            .collect()
+    }
+
+    /// every brain's fitness, the brains shared out between the threads
+    pub fn evaluate(&self) -> Vec<Fitness> {
+        let courses = self.courses();
+        let max_ticks = self.settings.max_ticks;
+        let mut fitness = vec![Fitness::default(); self.brains.len()];
+        let threads = self.settings.threads.max(1);
+        let chunk = self.brains.len().div_ceil(threads);
+        thread::scope(|s| {
+            for (brains, fitness) in self.brains.chunks(chunk).zip(fitness.chunks_mut(chunk)) {
+                let courses = &courses;
+                s.spawn(move || {
+                    for (brain, fitness) in brains.iter().zip(fitness) {
+                        *fitness = evaluate(brain, courses, max_ticks);
+                    }
+                });
+            }
+        });
+        fitness
+    }
+
+    /// Replace the brains with the next generation, going by `fitness`.
+    /// The elite are kept as they are, the rest are children of parents
+    /// picked by tournament, crossed over and mutated.
+    pub fn breed(&mut self, fitness: &[Fitness]) {
+        let mut ranked: Vec<usize> = (0..self.brains.len()).collect();
+        ranked.sort_by(|&a, &b| {
+            fitness[b]
+                .total()
+                .partial_cmp(&fitness[a].total())
+                .unwrap_or(Ordering::Equal)
+        });
+        let mut next: Vec<Brain> = ranked
+            .iter()
+            .take(self.settings.elite)
+            .map(|&i| self.brains[i].clone())
+            .collect();
+        while next.len() < self.brains.len() {
+            let a = self.tournament(fitness);
+            let b = self.tournament(fitness);
+            let mut child = self.brains[a].crossover(&self.brains[b], &mut self.rng);
+            let Settings {
+                mutation_rate,
+                mutation_strength,
+                ..
+            } = self.settings;
+            child.mutate(mutation_rate, mutation_strength, &mut self.rng);
+            next.push(child);
+        }
+        self.brains = next;
+        self.generation += 1;
+    }
+
+    fn tournament(&mut self, fitness: &[Fitness]) -> usize {
+        let n = self.brains.len();
+        (0..Population::TOURNAMENT)
+            .map(|_| self.rng.gen_range(0, n))
+            .max_by(|&a, &b| {
+                fitness[a]
+                    .total()
+                    .partial_cmp(&fitness[b].total())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            population: 8,
+            courses: 2,
+            threads: 1,
+            elite: 3,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn settings_must_leave_something_to_run() {
+        assert_eq!(Settings::default().check(), Ok(()));
+        let bad = [
+            Settings {
+                population: 0,
+                ..settings()
+            },
+            Settings {
+                courses: 0,
+                ..settings()
+            },
+            Settings {
+                max_ticks: 0,
+                ..settings()
+            },
+            Settings {
+                threads: 0,
+                ..settings()
+            },
+            Settings {
+                elite: 9,
+                ..settings()
+            },
+            Settings {
+                mutation_rate: 1.5,
+                ..settings()
+            },
+            Settings {
+                mutation_strength: 0.,
+                ..settings()
+            },
+            Settings {
+                mutation_strength: f32::NAN,
+                ..settings()
+            },
+        ];
+        for settings in &bad {
+            assert!(settings.check().is_err(), "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn breeding_keeps_the_elite() {
+        let mut population = Population::new(settings(), GameSeed(4));
+        let before = population.brains.clone();
+        // the later brains are fitter
+        let fitness: Vec<_> = (0..before.len())
+            .map(|i| Fitness {
+                distance: i as f32,
+                score: 0,
+            })
+            .collect();
+        population.breed(&fitness);
+        assert_eq!(population.generation, 1);
+        assert_eq!(population.brains.len(), before.len());
+        assert_eq!(
+            population.brains[..3],
+            [before[7].clone(), before[6].clone(), before[5].clone()]
+        );
+        // the children are new
+        assert!(population.brains[3..]
+            .iter()
+            .all(|b| !before[..5].contains(b)));
+    }
+
+    #[test]
+    fn champions_are_judged_on_courses_nobody_trains_on() {
+        let mut population = Population::new(settings(), GameSeed(4));
+        let held_out = population.held_out();
+        assert_eq!(held_out.len(), Population::HELD_OUT);
+        let fitness = vec![Fitness::default(); population.brains.len()];
+        for _ in 0..50 {
+            assert!(population.courses().iter().all(|c| !held_out.contains(c)));
+            population.breed(&fitness);
+            assert_eq!(population.held_out(), held_out);
+        }
+    }
+}
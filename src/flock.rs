@@ -0,0 +1,244 @@
+use crate::{
+    config::Config,
+    entity::Entity,
+    world::{InputState, World},
+};
+
+use std::cmp::Ordering;
+
+/// Many birds flying the same course at once. The course is moved on once
+/// a tick and every bird still flying flies through a copy of it in its
+/// own world, with its own score and power-ups, so they all see the same
+/// pipes and only the lead's world needs drawing. A crashed bird's world
+/// stops where it fell.
+#[derive(Debug, Clone)]
+pub struct Flock {
+    pub worlds: Vec<World>,
+    // the one course the birds share, its own bird goes unused
+    course: World,
+}
+
+impl Flock {
+    /// `size` birds, at least one, each starting from a copy of `world`
+    pub fn new(world: &World, size: usize) -> Self {
+        Flock {
+            worlds: vec![world.clone(); size.max(1)],
+            course: world.clone(),
+        }
+    }
+
+    /// Play a tick for every bird, each with its own input. The course
+    /// keeps pace with the birds still flying: it's at the highest level
+    /// any of them has reached, and slowed while any has slow-mo. A pickup
+    /// goes to whoever gets there first. Dead birds carry on falling until
+    /// they land.
+    pub fn step(&mut self, dt: f32, inputs: &[InputState]) {
+        let flying: Vec<&World> = self
+            .worlds
+            .iter()
+            .filter(|w| w.state.is_playing())
+            .collect();
+        if !flying.is_empty() {
+            self.course.level = flying.iter().map(|w| w.level).max().unwrap_or(0);
+            let time_scale = flying
+                .iter()
+                .map(|w| w.player.effects.time_scale())
+                .fold(1., f32::min);
+            self.course.step_course(dt, time_scale);
+        }
+
+        let course = &mut self.course;
+        for (world, &input) in self.worlds.iter_mut().zip(inputs) {
+            let flew = world.state.is_playing();
+            world.fly_through(course, dt, input);
+            if !flew {
+                continue;
+            }
+            // eaten pickups go from the course for everyone
+            for (shared, entity) in course.entities.iter_mut().zip(&world.entities) {
+                if matches!(entity, Entity::Decoration(_)) && shared != entity {
+                    shared.clone_from(entity);
+                }
+            }
+        }
+    }
+
+    /// swap in `config` for the course and every bird
+    pub fn apply_config(&mut self, config: Config) {
+        self.course.apply_config(config);
+        for world in &mut self.worlds {
+            world.apply_config(config);
+        }
+    }
+
+    /// birds still flying
+    pub fn alive(&self) -> usize {
+        self.worlds
+            .iter()
+            .filter(|world| !world.state.is_gameover())
+            .count()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.alive() == 0
+    }
+
+    /// The world the course is drawn from: the living bird that's got
+    /// furthest, or once they're all dead the one that got furthest.
+    pub fn lead(&self) -> &World {
+        // the course scrolls by lowering the offset
+        let furthest =
+            |a: &&World, b: &&World| a.offset.partial_cmp(&b.offset).unwrap_or(Ordering::Equal);
+        let alive = self.worlds.iter().filter(|w| !w.state.is_gameover());
+        alive
+            .min_by(furthest)
+            .or_else(|| self.worlds.iter().min_by(furthest))
+            .expect("a flock has at least one bird")
+    }
+
+    /// Once every bird is down, the one that flew furthest, with ties going
+    /// to the higher score. Nobody wins while a bird is still flying, or
+    /// when it's a dead heat.
+    pub fn winner(&self) -> Option<usize> {
+        if !self.is_over() {
+            return None;
+        }
+        let beats = |a: &World, b: &World| {
+            a.offset < b.offset || (a.offset == b.offset && a.score > b.score)
+        };
+        let best = (0..self.worlds.len()).fold(0, |best, i| {
+            if beats(&self.worlds[i], &self.worlds[best]) {
+                i
+            } else {
+                best
+            }
+        });
+        let tied = self
+            .worlds
+            .iter()
+            .enumerate()
+            .any(|(i, other)| i != best && !beats(&self.worlds[best], other));
+        if tied {
+            None
+        } else {
+            Some(best)
+        }
+    }
+
+    /// How far behind `lead` the bird in `world` is, in pixels. Birds
+    /// flying together are level, a dead one falls behind as the course
+    /// scrolls on without it.
+    pub fn behind(lead: &World, world: &World) -> f32 {
+        world.offset - lead.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        actors::{Apple, AppleKind, Pipe},
+        env::Env,
+        powerups::PowerUpKind,
+        seed::GameSeed,
+    };
+
+    const DT: f32 = 1. / crate::DESIRED_FPS as f32;
+
+    /// `size` birds on the course for seed 3, those in `ghosts` able to fly
+    /// through pipes for a while
+    fn birds(size: usize, ghosts: &[usize]) -> Flock {
+        let mut env = Env::default();
+        env.reset(GameSeed(3));
+        let mut flock = Flock::new(env.world(), size);
+        for &i in ghosts {
+            flock.worlds[i].player.effects.add(PowerUpKind::Ghost);
+        }
+        flock
+    }
+
+    /// a flap every `every` ticks, or never for 0
+    fn flapping(every: &[usize], tick: usize) -> Vec<InputState> {
+        every
+            .iter()
+            .map(|&n| InputState {
+                flap: n > 0 && tick.is_multiple_of(n),
+            })
+            .collect()
+    }
+
+    fn pipes(world: &World) -> Vec<Pipe> {
+        world.pipes().cloned().collect()
+    }
+
+    #[test]
+    fn the_lead_is_the_furthest_bird_alive() {
+        // the first bird never flaps, the second keeps itself up
+        let mut flock = birds(2, &[1]);
+        for tick in 0..150 {
+            flock.step(DT, &flapping(&[0, 15], tick));
+        }
+        assert_eq!(flock.alive(), 1);
+        assert!(flock.worlds[0].state.is_gameover());
+        let lead = flock.lead();
+        assert_eq!(lead.offset, flock.worlds[1].offset);
+        // left behind where it fell
+        assert!(Flock::behind(lead, &flock.worlds[0]) > 0.);
+        // no winner until the race is run
+        assert_eq!(flock.winner(), None);
+    }
+
+    #[test]
+    fn racers_share_one_course() {
+        let mut flock = birds(2, &[0, 1]);
+        for tick in 0..10 {
+            flock.step(DT, &flapping(&[20, 15], tick));
+        }
+        // an apple right in the first bird's way
+        let apple = Apple::new(flock.worlds[0].player_pos(), AppleKind::Purple);
+        flock.course.entities.push(Entity::Apple(apple));
+        flock.step(DT, &flapping(&[20, 15], 10));
+        assert_eq!(flock.worlds[0].bonus, 5);
+        assert_eq!(flock.worlds[1].bonus, 0);
+        // and gone for the other
+        assert!(matches!(
+            flock.course.entities.last(),
+            Some(Entity::Decoration(_))
+        ));
+
+        // the apple put the first bird a level up, the course keeps pace
+        let before = pipes(&flock.worlds[0]);
+        for tick in 11..200 {
+            flock.step(DT, &flapping(&[20, 15], tick));
+            let (a, b) = (&flock.worlds[0], &flock.worlds[1]);
+            assert_eq!(a.offset, b.offset);
+            assert_eq!(pipes(a), pipes(b));
+            assert!(std::ptr::eq(flock.lead(), a));
+        }
+        assert_eq!(flock.alive(), 2);
+        assert!(flock.worlds[0].level > flock.worlds[1].level);
+        assert_eq!(flock.course.level, flock.worlds[0].level);
+        let after = pipes(&flock.worlds[0]);
+        assert!(after.iter().any(|p| !before.contains(p)));
+    }
+
+    #[test]
+    fn the_furthest_bird_wins() {
+        // the middle bird holds on longest
+        let mut flock = birds(3, &[1]);
+        for tick in 0..300 {
+            let middle = if tick < 100 { 15 } else { 0 };
+            flock.step(DT, &flapping(&[0, middle, 0], tick));
+        }
+        assert!(flock.is_over());
+        assert_eq!(flock.winner(), Some(1));
+
+        // two birds that never flap crash together
+        let mut flock = birds(2, &[]);
+        for tick in 0..200 {
+            flock.step(DT, &flapping(&[0, 0], tick));
+        }
+        assert!(flock.is_over());
+        assert_eq!(flock.winner(), None);
+    }
+}
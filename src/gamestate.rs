@@ -10,34 +10,23 @@ pub enum GameState {
 
 impl GameState {
     pub fn is_playing(self) -> bool {
-        match self {
-            GameState::Playing => true,
-            _ => false,
-        }
+        matches!(self, GameState::Playing)
     }
     pub fn is_paused(self) -> bool {
-        match self {
-            GameState::Paused => true,
-            _ => false,
-        }
+        matches!(self, GameState::Paused)
     }
     pub fn is_gameover(self) -> bool {
-        match self {
-            GameState::GameOver => true,
-            _ => false,
-        }
+        matches!(self, GameState::GameOver)
     }
     pub fn is_countdown(self) -> bool {
-        match self {
-            GameState::Countdown => true,
-            _ => false,
-        }
+        matches!(self, GameState::Countdown)
     }
+    /// pause or resume a run, there's nothing to pause before or after one
     pub fn toggle_pause(&mut self) {
-        if self.is_paused() {
-            *self = GameState::Playing;
-        } else {
-            *self = GameState::Paused;
+        match self {
+            GameState::Paused => *self = GameState::Playing,
+            GameState::Playing => *self = GameState::Paused,
+            GameState::GameOver | GameState::Countdown => (),
         }
     }
 }
@@ -52,3 +41,24 @@ impl BitOr<Self> for GameState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_run_pauses() {
+        let mut state = GameState::Playing;
+        state.toggle_pause();
+        assert_eq!(state, GameState::Paused);
+        state.toggle_pause();
+        assert_eq!(state, GameState::Playing);
+
+        // pausing a finished run would resume it and end it a second time
+        for &other in &[GameState::GameOver, GameState::Countdown] {
+            let mut state = other;
+            state.toggle_pause();
+            assert_eq!(state, other);
+        }
+    }
+}
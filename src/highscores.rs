@@ -0,0 +1,179 @@
+use crate::{seed::GameSeed, util::user_data_file};
+
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: usize,
+    pub level: usize,
+    pub seed: GameSeed,
+    /// seconds since the unix epoch
+    pub date: u64,
+}
+
+impl Entry {
+    pub fn new(name: String, score: usize, level: usize, seed: GameSeed) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Entry {
+            name,
+            score,
+            level,
+            seed,
+            date,
+        }
+    }
+
+    /// `date` as YYYY-MM-DD
+    pub fn date_str(&self) -> String {
+        // days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = (self.date / 86_400) as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Best scores, kept in the user data directory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(default, rename = "entry")]
+    entries: Vec<Entry>,
+}
+
+impl HighScores {
+    pub const MAX_ENTRIES: usize = 10;
+    const FILE: &'static str = "highscores.toml";
+
+    /// Load the table, a missing file is an empty table. So is a file that
+    /// can't be read, after a warning, and it's moved aside rather than
+    /// overwritten by the next save.
+    pub fn load(ctx: &Context) -> GameResult<Self> {
+        let path = user_data_file(ctx, HighScores::FILE)?;
+        if !path.exists() {
+            return Ok(HighScores::default());
+        }
+        let loaded = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| HighScores::from_toml(&s));
+        match loaded {
+            Ok(scores) => Ok(scores),
+            Err(e) => {
+                let aside = path.with_extension("toml.bad");
+                println!("Could not read {}: {}", path.display(), e);
+                match fs::rename(&path, &aside) {
+                    Ok(()) => println!(
+                        "Starting a new table, the old one is in {}",
+                        aside.display()
+                    ),
+                    Err(e) => println!("Starting a new table, could not move the old one: {}", e),
+                }
+                Ok(HighScores::default())
+            }
+        }
+    }
+
+    /// the table in `s`, best first and no longer than `MAX_ENTRIES`
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        let mut scores: HighScores = toml::from_str(s).map_err(|e| e.to_string())?;
+        scores.entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+        scores.entries.truncate(HighScores::MAX_ENTRIES);
+        Ok(scores)
+    }
+
+    pub fn save(&self, ctx: &Context) -> GameResult<()> {
+        let path = user_data_file(ctx, HighScores::FILE)?;
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// would `score` make it onto the table
+    pub fn qualifies(&self, score: usize) -> bool {
+        score > 0
+            && (self.entries.len() < HighScores::MAX_ENTRIES
+                || self.entries.last().is_none_or(|e| score > e.score))
+    }
+
+    /// insert `entry` in order, returns its rank if it made the table
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|e| entry.score > e.score)
+            .unwrap_or(self.entries.len());
+        if rank >= HighScores::MAX_ENTRIES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(HighScores::MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: usize) -> Entry {
+        Entry::new(format!("bird {}", score), score, 1, GameSeed(score as u64))
+    }
+
+    #[test]
+    fn tables_load_best_first() {
+        let mut scores = HighScores::default();
+        for score in 1..=15 {
+            scores.entries.push(entry(score));
+        }
+        let loaded = HighScores::from_toml(&toml::to_string(&scores).unwrap()).unwrap();
+        let kept: Vec<_> = loaded.entries().iter().map(|e| e.score).collect();
+        assert_eq!(kept, (6..=15).rev().collect::<Vec<_>>());
+
+        assert!(HighScores::from_toml("[[entry]]\nname = 3").is_err());
+        assert_eq!(HighScores::from_toml("").unwrap(), HighScores::default());
+    }
+
+    #[test]
+    fn only_good_enough_scores_make_the_table() {
+        let mut scores = HighScores::default();
+        assert!(!scores.qualifies(0));
+        assert!(scores.qualifies(1));
+        for score in (1..=HighScores::MAX_ENTRIES).map(|s| s * 10) {
+            assert!(scores.insert(entry(score)).is_some());
+        }
+        assert_eq!(scores.entries()[0].score, 100);
+        assert!(!scores.qualifies(10));
+        assert!(scores.qualifies(11));
+        assert_eq!(scores.insert(entry(5)), None);
+        assert_eq!(scores.insert(entry(55)), Some(5));
+        assert_eq!(scores.entries().len(), HighScores::MAX_ENTRIES);
+        assert_eq!(scores.entries().last().unwrap().score, 20);
+    }
+
+    #[test]
+    fn dates_read_as_calendar_days() {
+        let mut e = entry(1);
+        e.date = 0;
+        assert_eq!(e.date_str(), "1970-01-01");
+        e.date = 951_782_400;
+        assert_eq!(e.date_str(), "2000-02-29");
+    }
+}
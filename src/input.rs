@@ -0,0 +1,487 @@
+use crate::util::user_config_file;
+
+use ggez::{
+    event::{Button, KeyCode, MouseButton},
+    Context, GameError, GameResult,
+};
+
+use std::{collections::BTreeMap, fmt, fs, str::FromStr};
+
+/// What the player wants done, whatever they pressed to ask for it
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Action {
+    Flap,
+    Pause,
+    Restart,
+    Screenshot,
+    Quit,
+    Mute,
+    VolumeUp,
+    VolumeDown,
+    SfxUp,
+    SfxDown,
+    Controls,
+    FlapModel,
+    /// the second bird's flap, in a race
+    FlapTwo,
+}
+
+impl Action {
+    /// in the order they're listed in the controls menu
+    pub const ALL: [Action; 13] = [
+        Action::Flap,
+        Action::FlapTwo,
+        Action::Pause,
+        Action::Restart,
+        Action::Screenshot,
+        Action::Quit,
+        Action::Mute,
+        Action::VolumeUp,
+        Action::VolumeDown,
+        Action::SfxUp,
+        Action::SfxDown,
+        Action::Controls,
+        Action::FlapModel,
+    ];
+
+    /// always left with a trigger, so a bad binding can't stop the game
+    /// or a race being played, quit or rebound
+    pub const ESSENTIAL: [Action; 4] = [
+        Action::Flap,
+        Action::FlapTwo,
+        Action::Quit,
+        Action::Controls,
+    ];
+
+    /// name in the bindings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Flap => "flap",
+            Action::Pause => "pause",
+            Action::Restart => "restart",
+            Action::Screenshot => "screenshot",
+            Action::Quit => "quit",
+            Action::Mute => "mute",
+            Action::VolumeUp => "volume_up",
+            Action::VolumeDown => "volume_down",
+            Action::SfxUp => "sfx_up",
+            Action::SfxDown => "sfx_down",
+            Action::Controls => "controls",
+            Action::FlapModel => "flap_model",
+            Action::FlapTwo => "flap_two",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Flap => "flap -- avoid the pipes!",
+            Action::Pause => "pause",
+            Action::Restart => "restart",
+            Action::Screenshot => "take a screenshot",
+            Action::Quit => "quit",
+            Action::Mute => "mute",
+            Action::VolumeUp => "turn the volume up",
+            Action::VolumeDown => "turn the volume down",
+            Action::SfxUp => "turn the sound effects up",
+            Action::SfxDown => "turn the sound effects down",
+            Action::Controls => "change the controls",
+            Action::FlapModel => "switch between flap models",
+            Action::FlapTwo => "flap the second bird in a race",
+        }
+    }
+}
+
+/// Something pressed on one of the input devices
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Trigger {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(Button),
+    /// a finger anywhere on a touchscreen
+    Touch,
+}
+
+// names for the triggers in the bindings file, the same as the variants
+macro_rules! names {
+    ($name:ident: $ty:ident [$($v:ident),* $(,)?]) => {
+        const $name: &[($ty, &str)] = &[$(($ty::$v, stringify!($v))),*];
+    };
+}
+
+names!(KEYS: KeyCode [
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Insert, Home, Delete, End, PageDown, PageUp, Left, Up, Right, Down,
+    Back, Return, Space, Tab,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    Add, Subtract, Multiply, Divide, Decimal, NumpadEnter,
+    Apostrophe, Backslash, Comma, Equals, Grave, LBracket, Minus, Period, RBracket,
+    Semicolon, Slash,
+    LAlt, LControl, LShift, RAlt, RControl, RShift,
+]);
+
+names!(BUTTONS: Button [
+    South, East, North, West, C, Z,
+    LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
+    Select, Start, Mode, LeftThumb, RightThumb,
+    DPadUp, DPadDown, DPadLeft, DPadRight,
+]);
+
+names!(MOUSE: MouseButton [Left, Right, Middle]);
+
+fn name_of<T: PartialEq>(names: &[(T, &'static str)], value: &T) -> Option<&'static str> {
+    names.iter().find(|(v, _)| v == value).map(|&(_, n)| n)
+}
+
+fn from_name<T: Copy>(names: &[(T, &str)], name: &str) -> Option<T> {
+    names.iter().find(|&&(_, n)| n == name).map(|&(v, _)| v)
+}
+
+/// `Key A`, `Mouse Left`, `Pad South` or `Touch`
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trigger::Key(k) => match name_of(KEYS, k) {
+                Some(name) => write!(f, "Key {}", name),
+                None => write!(f, "Key {:?}", k),
+            },
+            Trigger::Mouse(b) => match name_of(MOUSE, b) {
+                Some(name) => write!(f, "Mouse {}", name),
+                None => write!(f, "Mouse {:?}", b),
+            },
+            Trigger::Pad(b) => match name_of(BUTTONS, b) {
+                Some(name) => write!(f, "Pad {}", name),
+                None => write!(f, "Pad {:?}", b),
+            },
+            Trigger::Touch => write!(f, "Touch"),
+        }
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let trigger = match (words.next(), words.next(), words.next()) {
+            (Some("Touch"), None, None) => Some(Trigger::Touch),
+            (Some("Key"), Some(name), None) => from_name(KEYS, name).map(Trigger::Key),
+            (Some("Mouse"), Some(name), None) => from_name(MOUSE, name).map(Trigger::Mouse),
+            (Some("Pad"), Some(name), None) => from_name(BUTTONS, name).map(Trigger::Pad),
+            _ => None,
+        };
+        trigger.ok_or_else(|| format!("unknown key or button `{}`", s))
+    }
+}
+
+/// Which trigger does which action. A trigger does at most one action, an
+/// action can have any number of triggers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    bindings: Vec<(Trigger, Action)>,
+}
+
+impl Bindings {
+    const FILE: &'static str = "bindings.toml";
+
+    /// load the bindings from the user config directory, anything the file
+    /// leaves out keeps its default
+    pub fn load(ctx: &Context) -> GameResult<Self> {
+        let path = user_config_file(ctx, Bindings::FILE)?;
+        if !path.exists() {
+            return Ok(Bindings::default());
+        }
+        Bindings::from_toml(&fs::read_to_string(&path)?)
+            .map_err(|e| GameError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, ctx: &Context) -> GameResult<()> {
+        let path = user_config_file(ctx, Bindings::FILE)?;
+        fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    /// a table of action name to a list of triggers, like
+    /// `flap = ["Key A", "Mouse Left"]`
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        let table: BTreeMap<String, Vec<String>> = toml::from_str(s).map_err(|e| e.to_string())?;
+        let mut bindings = Bindings::default();
+        for (name, triggers) in table {
+            let action = Action::ALL
+                .iter()
+                .copied()
+                .find(|a| a.name() == name)
+                .ok_or_else(|| format!("unknown action `{}`", name))?;
+            bindings.bindings.retain(|&(_, a)| a != action);
+            for trigger in triggers {
+                bindings.set(action, trigger.parse()?);
+            }
+        }
+        bindings.keep_essentials();
+        Ok(bindings)
+    }
+
+    pub fn to_toml(&self) -> String {
+        Action::ALL
+            .iter()
+            .map(|&action| {
+                let triggers: Vec<_> = self
+                    .triggers(action)
+                    .map(|t| format!("{:?}", t.to_string()))
+                    .collect();
+                format!("{} = [{}]\n", action.name(), triggers.join(", "))
+            })
+            .collect()
+    }
+
+    pub fn action(&self, trigger: Trigger) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(t, _)| *t == trigger)
+            .map(|&(_, action)| action)
+    }
+
+    pub fn triggers(&self, action: Action) -> impl Iterator<Item = Trigger> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, a)| *a == action)
+            .map(|&(t, _)| t)
+    }
+
+    /// Make `trigger` do `action`, taking it off whatever it did before.
+    /// An essential action it leaves with nothing gets its defaults back.
+    pub fn bind(&mut self, action: Action, trigger: Trigger) {
+        self.set(action, trigger);
+        self.keep_essentials();
+    }
+
+    /// take every trigger off `action`, essential ones go back to their
+    /// defaults
+    pub fn clear(&mut self, action: Action) {
+        self.bindings.retain(|&(_, a)| a != action);
+        self.keep_essentials();
+    }
+
+    fn set(&mut self, action: Action, trigger: Trigger) {
+        self.bindings.retain(|&(t, _)| t != trigger);
+        self.bindings.push((trigger, action));
+    }
+
+    fn keep_essentials(&mut self) {
+        // no default trigger does two essential actions, so each one
+        // restored keeps its triggers and this runs out
+        let defaults = Bindings::default();
+        while let Some(&action) = Action::ESSENTIAL
+            .iter()
+            .find(|&&a| self.triggers(a).next().is_none())
+        {
+            for trigger in defaults.triggers(action) {
+                self.set(action, trigger);
+            }
+        }
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use self::{Action::*, Trigger::*};
+
+        Bindings {
+            bindings: vec![
+                (Key(KeyCode::A), Flap),
+                (Key(KeyCode::Return), Pause),
+                (Key(KeyCode::R), Restart),
+                (Key(KeyCode::P), Screenshot),
+                (Key(KeyCode::Escape), Quit),
+                (Key(KeyCode::M), Mute),
+                (Key(KeyCode::Equals), VolumeUp),
+                (Key(KeyCode::Minus), VolumeDown),
+                (Key(KeyCode::RBracket), SfxUp),
+                (Key(KeyCode::LBracket), SfxDown),
+                (Key(KeyCode::C), Controls),
+                (Key(KeyCode::F), FlapModel),
+                (Key(KeyCode::L), FlapTwo),
+                (Mouse(MouseButton::Left), Flap),
+                (Touch, Flap),
+                (Pad(Button::South), Flap),
+                (Pad(Button::Start), Pause),
+                (Pad(Button::West), Restart),
+                (Pad(Button::Select), Screenshot),
+                (Pad(Button::Mode), Quit),
+                (Pad(Button::DPadUp), VolumeUp),
+                (Pad(Button::DPadDown), VolumeDown),
+                (Pad(Button::DPadRight), SfxUp),
+                (Pad(Button::DPadLeft), SfxDown),
+                (Pad(Button::North), Controls),
+                (Pad(Button::East), FlapModel),
+                (Pad(Button::RightTrigger), FlapTwo),
+            ],
+        }
+    }
+}
+
+/// The controls screen, open over the pause or game over screen. The keys
+/// and buttons that drive it are fixed so it can't be locked out by a bad
+/// binding.
+#[derive(Debug, Clone, Default)]
+pub struct ControlsMenu {
+    /// index into `Action::ALL`
+    pub selected: usize,
+    /// the next press gets bound to the selected action
+    pub waiting: bool,
+}
+
+impl ControlsMenu {
+    /// handle a press while the menu is open, returns true when it closes
+    pub fn press(&mut self, bindings: &mut Bindings, trigger: Trigger) -> bool {
+        let action = Action::ALL[self.selected];
+        if self.waiting {
+            if trigger != Trigger::Key(KeyCode::Escape) {
+                bindings.bind(action, trigger);
+            }
+            self.waiting = false;
+            return false;
+        }
+
+        let len = Action::ALL.len();
+        match trigger {
+            Trigger::Key(KeyCode::Up) | Trigger::Pad(Button::DPadUp) => {
+                self.selected = (self.selected + len - 1) % len;
+            }
+            Trigger::Key(KeyCode::Down) | Trigger::Pad(Button::DPadDown) => {
+                self.selected = (self.selected + 1) % len;
+            }
+            Trigger::Key(KeyCode::Return) | Trigger::Pad(Button::South) => self.waiting = true,
+            Trigger::Key(KeyCode::Back)
+            | Trigger::Key(KeyCode::Delete)
+            | Trigger::Pad(Button::West) => bindings.clear(action),
+            Trigger::Key(KeyCode::Escape) | Trigger::Pad(Button::East) => return true,
+            _ => (),
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triggers(bindings: &Bindings, action: Action) -> Vec<Trigger> {
+        bindings.triggers(action).collect()
+    }
+
+    #[test]
+    fn triggers_round_trip_through_their_names() {
+        let all = KEYS
+            .iter()
+            .map(|&(k, _)| Trigger::Key(k))
+            .chain(MOUSE.iter().map(|&(b, _)| Trigger::Mouse(b)))
+            .chain(BUTTONS.iter().map(|&(b, _)| Trigger::Pad(b)))
+            .chain(Some(Trigger::Touch));
+        for trigger in all {
+            assert_eq!(trigger.to_string().parse(), Ok(trigger));
+        }
+        assert_eq!("Key Space".parse(), Ok(Trigger::Key(KeyCode::Space)));
+        assert_eq!("  Pad   South ".parse(), Ok(Trigger::Pad(Button::South)));
+        for bad in &["", "Key", "Key Nope", "Key A B", "Touch A", "Mouse South"] {
+            assert!(bad.parse::<Trigger>().is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn bindings_round_trip_through_toml() {
+        // the same triggers for each action, if not in the same order
+        let toml = Bindings::default().to_toml();
+        assert_eq!(Bindings::from_toml(&toml).unwrap().to_toml(), toml);
+        assert_eq!(Bindings::from_toml(""), Ok(Bindings::default()));
+    }
+
+    #[test]
+    fn the_file_replaces_the_actions_it_names() {
+        let bindings = Bindings::from_toml(
+            "flap = [\"Key Space\", \"Pad South\"]\nscreenshot = []\nmute = [\"Key A\"]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            triggers(&bindings, Action::Flap),
+            [Trigger::Key(KeyCode::Space), Trigger::Pad(Button::South)]
+        );
+        assert_eq!(triggers(&bindings, Action::Screenshot), []);
+        // mute took A, which flap no longer has anyway
+        assert_eq!(
+            bindings.action(Trigger::Key(KeyCode::A)),
+            Some(Action::Mute)
+        );
+        // anything left out keeps its default
+        assert_eq!(
+            bindings.action(Trigger::Key(KeyCode::Escape)),
+            Some(Action::Quit)
+        );
+
+        assert!(Bindings::from_toml("jump = [\"Key A\"]")
+            .unwrap_err()
+            .contains("jump"));
+        assert!(Bindings::from_toml("flap = [\"Key Nope\"]")
+            .unwrap_err()
+            .contains("Key Nope"));
+        assert!(Bindings::from_toml("flap = \"Key A\"").is_err());
+    }
+
+    #[test]
+    fn essential_actions_always_keep_a_trigger() {
+        let defaults = Bindings::default();
+        let bindings = Bindings::from_toml("quit = []\ncontrols = []\nflap_two = []").unwrap();
+        for &action in &[Action::Quit, Action::Controls, Action::FlapTwo] {
+            assert_eq!(triggers(&bindings, action), triggers(&defaults, action));
+        }
+
+        // taking the only controls trigger for flapping gives it back
+        let mut bindings = Bindings::from_toml("controls = [\"Key C\"]").unwrap();
+        bindings.bind(Action::Flap, Trigger::Key(KeyCode::C));
+        assert_eq!(
+            triggers(&bindings, Action::Controls),
+            triggers(&defaults, Action::Controls)
+        );
+        for &action in &Action::ESSENTIAL {
+            assert!(bindings.triggers(action).next().is_some());
+        }
+
+        // the second racer can flap from a pad too
+        assert_eq!(
+            defaults.action(Trigger::Pad(Button::RightTrigger)),
+            Some(Action::FlapTwo)
+        );
+    }
+
+    #[test]
+    fn the_menu_cant_lock_the_game_out() {
+        let mut bindings = Bindings::default();
+        let mut menu = ControlsMenu::default();
+        let back = Trigger::Key(KeyCode::Back);
+        for _ in 0..Action::ALL.len() {
+            assert!(!menu.press(&mut bindings, back));
+            assert!(!menu.press(&mut bindings, Trigger::Key(KeyCode::Down)));
+        }
+        assert_eq!(menu.selected, 0);
+        for action in Action::ALL.iter() {
+            let left = bindings.triggers(*action).next().is_some();
+            assert_eq!(left, Action::ESSENTIAL.contains(action), "{:?}", action);
+        }
+
+        // rebinding waits for the next press, escape cancels it
+        assert!(!menu.press(&mut bindings, Trigger::Pad(Button::South)));
+        assert!(!menu.press(&mut bindings, Trigger::Key(KeyCode::Escape)));
+        assert_eq!(
+            bindings.action(Trigger::Key(KeyCode::Escape)),
+            Some(Action::Quit)
+        );
+        assert!(!menu.press(&mut bindings, Trigger::Key(KeyCode::Return)));
+        assert!(!menu.press(&mut bindings, Trigger::Key(KeyCode::Space)));
+        assert_eq!(
+            bindings.action(Trigger::Key(KeyCode::Space)),
+            Some(Action::Flap)
+        );
+        assert!(menu.press(&mut bindings, Trigger::Key(KeyCode::Escape)));
+    }
+}
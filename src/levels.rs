@@ -0,0 +1,182 @@
+use crate::error::{GameError, GameResult};
+
+#[cfg(feature = "window")]
+use ggez::{filesystem, Context};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "window")]
+use std::io::Read;
+
+/// Difficulty settings that kick in once the score reaches `score`. The
+/// rest are multipliers on the base pipe and speed settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub score: usize,
+    pub gap: f32,
+    pub spacing: f32,
+    pub speed: f32,
+    pub margin: f32,
+    /// chance of a pipe pair that moves, from 0 to 1
+    #[serde(default)]
+    pub moving: f32,
+}
+
+impl Level {
+    /// the multipliers have to be positive and `moving` a chance
+    fn check(&self) -> Result<(), String> {
+        let multipliers = [
+            ("gap", self.gap),
+            ("spacing", self.spacing),
+            ("speed", self.speed),
+            ("margin", self.margin),
+        ];
+        for &(name, value) in multipliers.iter() {
+            if !(value.is_finite() && value > 0.) {
+                return Err(format!("{} must be above 0, got {}", name, value));
+            }
+        }
+        if !(0. ..=1.).contains(&self.moving) {
+            return Err(format!("moving must be from 0 to 1, got {}", self.moving));
+        }
+        Ok(())
+    }
+}
+
+/// The difficulty curve, see `resources/levels.toml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Levels {
+    #[serde(rename = "level")]
+    levels: Vec<Level>,
+}
+
+impl Levels {
+    /// load `/levels.toml` from the resources, falling back to the bundled copy
+    #[cfg(feature = "window")]
+    pub fn load(ctx: &mut Context) -> GameResult<Self> {
+        if !filesystem::is_file(ctx, "/levels.toml") {
+            return Ok(Levels::default());
+        }
+        let mut s = String::new();
+        filesystem::open(ctx, "/levels.toml")?.read_to_string(&mut s)?;
+        Levels::from_toml(&s)
+    }
+
+    pub fn from_toml(s: &str) -> GameResult<Self> {
+        let levels: Levels = toml::from_str(s)?;
+        match levels.levels.first() {
+            None => Err(GameError::ConfigError(
+                "levels: need at least one [[level]]".to_string(),
+            )),
+            Some(first) if first.score != 0 => Err(GameError::ConfigError(
+                "levels: the first level must start at score 0".to_string(),
+            )),
+            _ if levels.levels.windows(2).any(|w| w[0].score >= w[1].score) => Err(
+                GameError::ConfigError("levels: scores must be increasing".to_string()),
+            ),
+            _ => {
+                for (i, level) in levels.levels.iter().enumerate() {
+                    level.check().map_err(|e| {
+                        GameError::ConfigError(format!("levels: level {}: {}", i + 1, e))
+                    })?;
+                }
+                Ok(levels)
+            }
+        }
+    }
+
+    /// the largest `margin` of any level
+    pub fn max_margin(&self) -> f32 {
+        self.levels.iter().map(|l| l.margin).fold(0., f32::max)
+    }
+
+    /// the smallest `spacing` of any level
+    pub fn min_spacing(&self) -> f32 {
+        self.levels
+            .iter()
+            .map(|l| l.spacing)
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// index of the level reached with `score`
+    pub fn level_for(&self, score: usize) -> usize {
+        self.levels
+            .iter()
+            .rposition(|l| l.score <= score)
+            .unwrap_or(0)
+    }
+
+    pub fn get(&self, level: usize) -> &Level {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels::from_toml(include_str!("../resources/levels.toml"))
+            .expect("bundled levels.toml is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(score: usize, fields: &str) -> String {
+        format!(
+            "[[level]]\nscore = {}\ngap = 1.0\nspacing = 1.0\nspeed = 1.0\nmargin = 1.0\n{}\n",
+            score, fields
+        )
+    }
+
+    fn rejects(s: &str, message: &str) {
+        match Levels::from_toml(s) {
+            Err(GameError::ConfigError(e)) => assert!(e.contains(message), "{:?}", e),
+            other => panic!("expected an error about {}, got {:?}", message, other),
+        }
+    }
+
+    #[test]
+    fn bundled_levels_load() {
+        let levels = Levels::default();
+        assert_eq!(levels.level_for(0), 0);
+        assert!(levels.level_for(1000) > 0);
+        assert_eq!(levels.get(usize::MAX), levels.get(levels.level_for(1000)));
+    }
+
+    #[test]
+    fn scores_start_at_zero_and_increase() {
+        rejects("level = []", "at least one");
+        rejects(&level(3, ""), "score 0");
+        rejects(
+            &(level(0, "") + &level(5, "") + &level(5, "")),
+            "increasing",
+        );
+        let levels = Levels::from_toml(&(level(0, "") + &level(5, ""))).unwrap();
+        assert_eq!(levels.level_for(4), 0);
+        assert_eq!(levels.level_for(5), 1);
+    }
+
+    #[test]
+    fn multipliers_must_be_positive() {
+        // a `speed = 0` would hang the spawner working out what's in reach
+        rejects(
+            &(level(0, "") + &level(5, "").replace("speed = 1.0", "speed = 0.0")),
+            "level 2: speed",
+        );
+        rejects(&level(0, "").replace("gap = 1.0", "gap = -1.0"), "gap");
+        rejects(
+            &level(0, "").replace("spacing = 1.0", "spacing = nan"),
+            "spacing",
+        );
+        rejects(
+            &level(0, "").replace("margin = 1.0", "margin = inf"),
+            "margin",
+        );
+        rejects(&level(0, "moving = 1.5"), "moving");
+        let levels = Levels::from_toml(
+            &(level(0, "") + &level(5, "").replace("margin = 1.0", "margin = 1.5")),
+        )
+        .unwrap();
+        assert_eq!(levels.max_margin(), 1.5);
+    }
+}
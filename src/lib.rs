@@ -0,0 +1,50 @@
+//! The game rules and everything the game is drawn with. The `flappy_bird`
+//! binary plays it in a window, `env` runs it headless for agents. The
+//! window, sound and input parts need the default `window` feature.
+pub mod actors;
+#[cfg(feature = "window")]
+pub mod assets;
+#[cfg(feature = "window")]
+pub mod audio;
+pub mod brain;
+#[cfg(feature = "window")]
+pub mod cli;
+pub mod collision;
+pub mod config;
+pub mod controller;
+pub mod entity;
+pub mod env;
+pub mod error;
+#[cfg(feature = "window")]
+pub mod events;
+pub mod evolve;
+pub mod flock;
+pub mod gamestate;
+#[cfg(feature = "window")]
+pub mod highscores;
+#[cfg(feature = "window")]
+pub mod input;
+pub mod levels;
+pub mod powerups;
+pub mod replay;
+pub mod seed;
+pub mod util;
+pub mod validate;
+pub mod world;
+
+// game constants
+pub const PLAYER_LIFE: f32 = 1.;
+pub const FALL_SPEED: f32 = 18.;
+pub const FLAP_SPEED: f32 = 320.;
+pub const FLAP_TIMEOUT: f32 = 0.35;
+
+pub const DESIRED_FPS: u32 = 60;
+pub const MOVE_SPEED: f32 = 2.;
+
+pub const PLAYER_BBOX: f32 = 12.;
+pub const PIPE_BBOX: f32 = 12.;
+
+/// height of the bundled ground sprite, drawn below the ground line
+pub const BASE_HEIGHT: f32 = 112.;
+pub const SCREEN_HEIGHT: f32 = 624.;
+pub const SCREEN_WIDTH: f32 = 1008.;
@@ -1,112 +1,406 @@
 #![allow(dead_code)]
-mod actors;
-mod assets;
-mod gamestate;
-mod util;
-
-use crate::{
-    actors::{Actor, Pipe, Player},
-    assets::Assets,
+use flappy_bird::{
+    actors::Actor,
+    assets::{Assets, PlayerAssets},
+    audio::{Audio, AudioSettings},
+    brain::Brain,
+    cli::Args,
+    config::{Config, ConfigWatcher},
+    controller::{Autopilot, Controller},
+    entity::Sprite,
+    env::Observation,
+    events::{self, InputHandler},
+    flock::Flock,
     gamestate::GameState,
-    util::translate_coords,
+    highscores::{Entry, HighScores},
+    input::{Action, Bindings, ControlsMenu, Trigger},
+    levels::Levels,
+    powerups::PowerUpKind,
+    replay::{self, Playback, Recorder, Replay},
+    seed::GameSeed,
+    util::{translate_coords, user_data_file},
+    world::{Dimensions, InputState, World},
+    DESIRED_FPS,
 };
 
 use ggez::{
     conf,
-    event::{self, EventHandler, KeyCode, KeyMods, MouseButton},
-    graphics::{self, DrawParam},
-    nalgebra::{self as na, Point2},
-    timer, {Context, ContextBuilder, GameResult},
+    event::{winit_event::TouchPhase, Button, EventHandler, KeyCode, KeyMods, MouseButton},
+    graphics::{self, DrawParam, Rect},
+    nalgebra::{Point2, Vector2},
+    timer, {Context, ContextBuilder, GameError, GameResult},
 };
 
-use std::{env, path};
+use std::{env, fs, path, sync::Arc};
 
-// game constants
-pub const PLAYER_LIFE: f32 = 1.;
-pub const FALL_SPEED: f32 = 18.;
-pub const FLAP_SPEED: f32 = 320.;
-pub const FLAP_TIMEOUT: f32 = 0.35;
-
-pub const DESIRED_FPS: u32 = 60;
-pub const MOVE_SPEED: f32 = 2.;
-
-pub const PLAYER_BBOX: f32 = 12.;
-pub const PIPE_BBOX: f32 = 12.;
-
-pub const SCREEN_HEIGHT: f32 = 624.;
-pub const SCREEN_WIDTH: f32 = 1008.;
-
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-struct InputState {
-    flap: bool,
-}
-
-impl Default for InputState {
-    fn default() -> Self {
-        InputState { flap: false }
-    }
-}
+// how long the config reload notice stays up
+const NOTICE_TICKS: u32 = 4 * DESIRED_FPS;
+// how long an autoplayed run shows its game over screen
+const AUTOPLAY_RESTART_TICKS: u32 = 3 * DESIRED_FPS;
 
 #[derive(Debug)]
 struct FlappyBird {
-    player: Player,
-    pipes: Vec<(Pipe, Pipe)>, // pipe & upside down pipe
-    level: usize,
-    score: usize,
+    world: World,
     assets: Assets,
-    screen_width: f32,
-    screen_height: f32,
+    audio: Audio,
     input: InputState,
-    flap_timeout: f32,
-    offset: f32,
-    frames: u64,
-    state: GameState,
+    // the second player's, in a --race
+    input_two: InputState,
+    bindings: Bindings,
+    // open over the pause and game over screens to rebind the controls
+    controls: Option<ControlsMenu>,
+    recorder: Recorder,
+    // input comes from here instead of the keyboard when watching a replay
+    playback: Option<Playback>,
+    // or from here with --autoplay or --brain
+    controller: Option<Box<dyn Controller>>,
+    // the --brain flying, drawn over the game
+    brain: Option<Brain>,
+    // with --flock or --race, birds flying the course alongside each other,
+    // and the controllers flying a --flock
+    flock: Option<Flock>,
+    pilots: Vec<Box<dyn Controller>>,
+    // ticks until an autoplayed game over restarts
+    restart_ticks: u32,
+    config: Config,
+    // reloads the --config file when it changes
+    watcher: Option<ConfigWatcher>,
+    // lines shown in the corner after a reload, and for how many more ticks
+    notice: Vec<String>,
+    notice_ticks: u32,
+    highscores: HighScores,
+    // name typed so far when a run makes the high score table
+    name_entry: Option<String>,
+    args: Args,
 }
 
 impl FlappyBird {
-    fn new(ctx: &mut Context) -> GameResult<FlappyBird> {
+    fn new(
+        ctx: &mut Context,
+        args: Args,
+        config: Config,
+        audio: AudioSettings,
+    ) -> GameResult<FlappyBird> {
         println!("Game resource path: {:?}", ctx.filesystem);
 
-        print_instructions();
+        let bindings = Bindings::load(ctx)?;
+        print_instructions(&bindings);
+
+        let levels = Levels::load(ctx)?;
+        config
+            .validate_levels(&levels)
+            .map_err(GameError::ConfigError)?;
+        let replay = match args.replay {
+            Some(ref path) => Some(Replay::read(&mut fs::File::open(path)?)?),
+            None => None,
+        };
+        // a replay plays under the flap model it was recorded with
+        let config = match replay {
+            Some(ref replay) => replay.config(config),
+            None => config,
+        };
+        let rules = replay::rules_hash(&config, &levels);
+        let playback = match replay {
+            Some(replay) => Some(Playback::new(replay, rules)?),
+            None => None,
+        };
+        // a fixed seed replays the same course on every restart
+        let seed = match playback {
+            Some(ref playback) => playback.replay.seed,
+            None => args.seed.unwrap_or_else(GameSeed::random),
+        };
+        println!("Seed: {}", seed);
+
+        let assets = Assets::new(ctx, seed)?;
+        let dims = Dimensions {
+            screen_width: ctx.conf.window_mode.width,
+            screen_height: ctx.conf.window_mode.height,
+            ground: f32::from(assets.bg.bg_h),
+            pipe_h: f32::from(assets.bg.pipe_img.height()) / 2.,
+        };
+
+        let mut world = World::new(dims, seed, levels, config);
+        world.masks = Some(Arc::new(Assets::masks(ctx)?));
 
-        let assets = Assets::new(ctx)?;
-        let player = Player::new();
-        let screen_width = ctx.conf.window_mode.width;
-        let screen_height = ctx.conf.window_mode.height;
-        let pipes = actors::gen_pipes(&assets, screen_width);
+        let pilots: Vec<Box<dyn Controller>> = match args.flock {
+            Some(ref path) => Brain::load_generation(path)?
+                .into_iter()
+                .map(|brain| Box::new(brain) as Box<dyn Controller>)
+                .collect(),
+            None => Vec::new(),
+        };
+        // a flock flies instead of a lone brain or the autopilot
+        let brain = match args.brain {
+            Some(ref path) if pilots.is_empty() => Some(Brain::load(path)?),
+            _ => None,
+        };
+        let controller: Option<Box<dyn Controller>> = match brain {
+            Some(ref brain) => Some(Box::new(brain.clone())),
+            None if args.autoplay && pilots.is_empty() => Some(Box::new(Autopilot::default())),
+            None => None,
+        };
 
-        let s = FlappyBird {
-            player,
-            pipes,
-            level: 0,
-            score: 0,
+        let mut s = FlappyBird {
+            world,
             assets,
-            screen_width,
-            screen_height,
+            audio: Audio::new(ctx, audio)?,
             input: InputState::default(),
-            flap_timeout: 0.,
-            offset: 0.,
-            frames: 0,
-            state: GameState::Paused,
+            input_two: InputState::default(),
+            bindings,
+            controls: None,
+            recorder: Recorder::new(seed, rules, config.physics.flap_model),
+            playback,
+            controller,
+            brain,
+            flock: None,
+            pilots,
+            restart_ticks: 0,
+            config,
+            watcher: args.config.as_ref().map(ConfigWatcher::new),
+            notice: Vec::new(),
+            notice_ticks: 0,
+            highscores: HighScores::load(ctx)?,
+            name_entry: None,
+            args,
         };
+        if s.playback.is_some() || s.controller.is_some() || !s.pilots.is_empty() {
+            s.world.update_state(GameState::Playing);
+        }
+        // a race starts from the menu like a game, and its birds set off
+        // once the countdown is done
+        let birds = if s.args.race { 2 } else { s.pilots.len() };
+        if birds > 0 {
+            let mut start = s.world.clone();
+            start.state = GameState::Playing;
+            s.flock = Some(Flock::new(&start, birds));
+        }
 
         Ok(s)
     }
 
     fn restart(&mut self, ctx: &mut Context) -> GameResult<()> {
-        *self = FlappyBird::new(ctx)?;
-        self.update_state(GameState::Playing);
+        *self = FlappyBird::new(ctx, self.args.clone(), self.config, self.audio.settings)?;
+        self.world.update_state(GameState::Playing);
+        Ok(())
+    }
+
+    fn game_over(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let (true, Some(flock)) = (self.args.race, &self.flock) {
+            // races aren't recorded, one replay holds one bird's input
+            println!("Game over! {}", race_result(flock));
+            return Ok(());
+        }
+        let score = self.world.score as u64;
+        let tick = self.world.ticks;
+        println!(
+            "Game over! Score: {} -- replay this course with --seed {}",
+            score, self.world.seed
+        );
+
+        if self.controller.is_some() || self.flock.is_some() {
+            // demo runs aren't worth keeping
+            self.restart_ticks = AUTOPLAY_RESTART_TICKS;
+            return Ok(());
+        }
+        match self.playback {
+            Some(ref playback) => {
+                let recorded = &playback.replay;
+                if recorded.score == score && recorded.end_tick == tick {
+                    println!("Replay matches the recorded run");
+                } else {
+                    println!(
+                        "Replay diverged: recorded score {} ending on tick {}, got score {} on tick {}",
+                        recorded.score, recorded.end_tick, score, tick
+                    );
+                }
+            }
+            None => {
+                let path = user_data_file(ctx, &format!("replay-{}.fbr", self.world.seed))?;
+                let replay = self.recorder.finish(score, tick);
+                replay.write(&mut fs::File::create(&path)?)?;
+                println!("Replay saved to {}", path.display());
+
+                if self.highscores.qualifies(self.world.score) {
+                    self.name_entry = Some(String::new());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A recorded run or a replay has to keep the rules it started with,
+    /// or the replay won't play back the same.
+    fn rules_locked(&self) -> bool {
+        let recording = self.controller.is_none()
+            && self.flock.is_none()
+            && self.world.ticks > 0
+            && !self.world.state.is_gameover();
+        self.playback.is_some() || recording
+    }
+
+    fn reload_config(&mut self, result: GameResult<Config>) {
+        let levels = &self.world.levels;
+        let result = result.and_then(|config| {
+            config
+                .validate_levels(levels)
+                .map(|()| config)
+                .map_err(GameError::ConfigError)
+        });
+        self.notice = match result {
+            Ok(config) => {
+                let mut changes = self.config.diff(&config);
+                if changes.is_empty() {
+                    return;
+                }
+                if config.screen != self.config.screen {
+                    changes.push("(screen size needs a relaunch)".to_string());
+                }
+                self.apply_config(config);
+                changes.insert(0, "Config reloaded:".to_string());
+                changes
+            }
+            Err(e) => vec!["Config not reloaded:".to_string(), e.to_string()],
+        };
+        for line in &self.notice {
+            println!("{}", line);
+        }
+        self.notice_ticks = NOTICE_TICKS;
+    }
+
+    /// swap in `config` for the game and every bird in a flock. Only called
+    /// while the rules aren't locked, so nothing is recorded yet and the
+    /// recorder can start over under the new rules.
+    fn apply_config(&mut self, config: Config) {
+        self.world.apply_config(config);
+        if let Some(ref mut flock) = self.flock {
+            flock.apply_config(config);
+        }
+        self.config = config;
+        self.recorder = Recorder::new(
+            self.world.seed,
+            replay::rules_hash(&config, &self.world.levels),
+            config.physics.flap_model,
+        );
+    }
+
+    fn trigger_down(&mut self, ctx: &mut Context, trigger: Trigger) {
+        if let Some(ref mut menu) = self.controls {
+            if menu.press(&mut self.bindings, trigger) {
+                self.controls = None;
+                self.bindings.save(ctx).expect("Could not save bindings");
+            }
+            return;
+        }
+        if let Some(action) = self.bindings.action(trigger) {
+            self.action_down(ctx, action);
+        }
+    }
+
+    fn trigger_up(&mut self, trigger: Trigger) {
+        match self.bindings.action(trigger) {
+            Some(Action::Flap) => self.input.flap = false,
+            Some(Action::FlapTwo) => self.input_two.flap = false,
+            _ => (),
+        }
+    }
+
+    fn action_down(&mut self, ctx: &mut Context, action: Action) {
+        // pads and touchscreens can still confirm or skip entering a name,
+        // the keyboard types it
+        if self.name_entry.is_some() {
+            match action {
+                Action::Pause => self.submit_name(ctx).expect("Could not save high scores"),
+                Action::Quit => self.name_entry = None,
+                _ => (),
+            }
+            return;
+        }
+        match action {
+            Action::Flap => {
+                if self.world.state.is_paused() {
+                    self.world.update_state(GameState::Playing);
+                }
+                self.input.flap = true;
+            }
+            Action::FlapTwo if self.args.race => {
+                if self.world.state.is_paused() {
+                    self.world.update_state(GameState::Playing);
+                }
+                self.input_two.flap = true;
+            }
+            Action::FlapTwo => (),
+            Action::Screenshot => {
+                let img = graphics::screenshot(ctx).expect("Could not take screenshot");
+                img.encode(ctx, graphics::ImageFormat::Png, "/screenshot.png")
+                    .expect("Could not save screenshot");
+            }
+            Action::Restart if self.world.state.is_gameover() => {
+                self.restart(ctx).expect("Restart failed");
+            }
+            Action::Restart => (),
+            Action::Pause => self.world.state.toggle_pause(),
+            Action::Mute => self.change_audio(ctx, AudioSettings::toggle_mute),
+            Action::VolumeUp => self.change_audio(ctx, AudioSettings::volume_up),
+            Action::VolumeDown => self.change_audio(ctx, AudioSettings::volume_down),
+            Action::SfxUp => self.change_audio(ctx, AudioSettings::sfx_up),
+            Action::SfxDown => self.change_audio(ctx, AudioSettings::sfx_down),
+            Action::Quit => ggez::quit(ctx),
+            Action::Controls if !self.world.state.is_playing() => {
+                self.input.flap = false;
+                self.controls = Some(ControlsMenu::default());
+            }
+            Action::Controls => (),
+            Action::FlapModel if self.rules_locked() => {
+                self.notice = vec!["flap model can't change mid-run".to_string()];
+                self.notice_ticks = NOTICE_TICKS;
+            }
+            Action::FlapModel if !self.world.state.is_playing() => {
+                let mut config = self.config;
+                config.physics.flap_model = config.physics.flap_model.next();
+                self.apply_config(config);
+                self.notice = vec![format!("flap model: {}", self.config.physics.flap_model)];
+                self.notice_ticks = NOTICE_TICKS;
+            }
+            Action::FlapModel => (),
+        }
+    }
+
+    /// change the audio settings and keep them for next time
+    fn change_audio(&mut self, ctx: &mut Context, change: fn(&mut AudioSettings)) {
+        change(&mut self.audio.settings);
+        self.audio
+            .settings
+            .save(ctx)
+            .expect("Could not save audio settings");
+    }
+
+    fn submit_name(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let Some(name) = self.name_entry.take() {
+            let name = if name.is_empty() {
+                "anonymous".to_string()
+            } else {
+                name
+            };
+            let entry = Entry::new(
+                name,
+                self.world.score,
+                self.world.level + 1,
+                self.world.seed,
+            );
+            self.highscores.insert(entry);
+            self.highscores.save(ctx)?;
+        }
         Ok(())
     }
 
     fn draw_bg(&mut self, ctx: &mut Context) -> GameResult<()> {
         self.assets.bg.bg.clear();
         let bg = &mut self.assets.bg;
-        let first_bg = -1. * (self.offset - (self.offset % f32::from(bg.bg_w)));
+        let first_bg = -(self.world.offset - (self.world.offset % f32::from(bg.bg_w)));
         // draw up to 2 panels ahead
         for i in 0..=2 {
             // draw bg
-            for tile in 0..=(self.screen_width as u16 / bg.bg_w) {
+            for tile in 0..=(self.world.dims.screen_width as u16 / bg.bg_w) {
                 let bg_params = DrawParam::new().dest(Point2::new(
                     first_bg + f32::from(i * bg.bg_w) + f32::from(tile * bg.bg_w),
                     0.,
@@ -117,12 +411,12 @@ impl FlappyBird {
         graphics::draw(
             ctx,
             &bg.bg,
-            DrawParam::new().dest(Point2::new(self.offset, 0.)),
+            DrawParam::new().dest(Point2::new(self.world.offset, 0.)),
         )?;
         graphics::draw(
             ctx,
             &bg.base,
-            DrawParam::new().dest(Point2::new(self.offset, 0.)),
+            DrawParam::new().dest(Point2::new(self.world.offset, 0.)),
         )?;
 
         Ok(())
@@ -131,11 +425,11 @@ impl FlappyBird {
     fn draw_base(&mut self, ctx: &mut Context) -> GameResult<()> {
         self.assets.bg.base.clear();
         let bg = &mut self.assets.bg;
-        let first_base = -1. * (self.offset - (self.offset % f32::from(bg.base_w)));
+        let first_base = -(self.world.offset - (self.world.offset % f32::from(bg.base_w)));
         // draw up to 2 panels ahead
         for i in 0..=2 {
             // draw base
-            for tile in 0..=(self.screen_width as u16 / bg.base_w) {
+            for tile in 0..=(self.world.dims.screen_width as u16 / bg.base_w) {
                 let base_params = DrawParam::new().dest(Point2::new(
                     first_base + f32::from(i * bg.base_w) + f32::from(tile * bg.base_w),
                     f32::from(bg.bg_h),
@@ -146,37 +440,82 @@ impl FlappyBird {
         graphics::draw(
             ctx,
             &bg.base,
-            DrawParam::new().dest(Point2::new(self.offset, 0.)),
+            DrawParam::new().dest(Point2::new(self.world.offset, 0.)),
         )?;
 
         Ok(())
     }
 
-    fn draw_pipes(&mut self, ctx: &mut Context) -> GameResult<()> {
+    /// Everything on the course, batched by sprite. Pipe space is screen
+    /// space scrolled by the offset, so the batches are drawn at it.
+    fn draw_entities(&mut self, ctx: &mut Context) -> GameResult<()> {
         self.assets.bg.pipe.clear();
-        let pipe_batch = &mut self.assets.bg.pipe;
+        for batch in self.assets.apples.iter_mut() {
+            batch.clear();
+        }
+        let offset = Point2::new(self.world.offset, 0.);
+        let pipe_h = self.world.dims.pipe_h;
+        // the bottom half of the sprite is plain pipe, drawn past the end of
+        // each pipe so openings far from the centre don't leave a hole
+        let extension = Rect::new(0., 0.5, 1., 0.5);
+        // power-ups are drawn as badges after the sprites
+        let mut badges = Vec::new();
 
-        for (btm, top) in &self.pipes {
-            // place pipes by the center of their sprite
-            let btm_param = DrawParam::new()
-                .dest(btm.pos)
-                .rotation(btm.facing)
-                .offset(Point2::new(0.5, 0.5));
+        for entity in &self.world.entities {
+            // place actors by the center of their sprite
+            let params = DrawParam::new()
+                .dest(entity.pos())
+                .rotation(entity.facing())
+                .offset(Point2::new(0.5, 0.5))
+                .color(graphics::Color::new(1., 1., 1., entity.alpha()));
+            match entity.sprite() {
+                Sprite::Pipe => {
+                    let far_end = match entity.as_pipe() {
+                        Some(pipe) => pipe.away_from_gap() * pipe_h * 1.5,
+                        None => Vector2::zeros(),
+                    };
+                    let ext = params.src(extension).dest(entity.pos() + far_end);
+                    self.assets.bg.pipe.add(ext);
+                    self.assets.bg.pipe.add(params);
+                }
+                // the sprites are twice the size of the bird, scale them to
+                // the apple's box
+                Sprite::Apple(kind) => {
+                    self.assets
+                        .apple(kind)
+                        .add(params.scale(Vector2::new(0.5, 0.5)));
+                }
+                Sprite::PowerUp(kind) => {
+                    badges.push((kind, entity.pos(), entity.bbox_size().x, entity.alpha()))
+                }
+                Sprite::Bird => (),
+            }
+        }
 
-            let top_param = DrawParam::new()
-                .dest(top.pos)
-                .rotation(top.facing)
-                .offset(Point2::new(0.5, 0.5));
+        graphics::draw(ctx, &self.assets.bg.pipe, DrawParam::new().dest(offset))?;
+        for batch in &self.assets.apples {
+            graphics::draw(ctx, batch, DrawParam::new().dest(offset))?;
+        }
+        if badges.is_empty() {
+            return Ok(());
+        }
 
-            pipe_batch.add(btm_param);
-            pipe_batch.add(top_param);
+        let mut mesh = graphics::MeshBuilder::new();
+        for &(kind, pos, radius, alpha) in &badges {
+            let mut color = powerup_color(kind);
+            color.a *= alpha;
+            mesh.circle(graphics::DrawMode::fill(), pos, radius, 0.5, color);
         }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::new().dest(offset))?;
 
-        graphics::draw(
-            ctx,
-            &self.assets.bg.pipe,
-            DrawParam::new().dest(Point2::new(self.offset, 0.)),
-        )?;
+        for &(kind, pos, _, alpha) in &badges {
+            let text = graphics::Text::new((powerup_label(kind), self.assets.font, 14.));
+            let (w, h) = text.dimensions(ctx);
+            let dest = Point2::new(pos.x + offset.x - w as f32 / 2., pos.y - h as f32 / 2.);
+            let color = graphics::Color::new(0., 0., 0., alpha);
+            graphics::draw(ctx, &text, (dest, 0., color))?;
+        }
         Ok(())
     }
 
@@ -185,8 +524,8 @@ impl FlappyBird {
         let params = DrawParam::new()
             .dest(translate_coords(
                 Point2::origin(),
-                self.screen_width,
-                self.screen_height - (f32::from(msg.height()) / 2.) + 35.,
+                self.world.dims.screen_width,
+                self.world.dims.screen_height - (f32::from(msg.height()) / 2.) + 35.,
             ))
             .offset(Point2::new(0.5, 0.5));
         graphics::draw(ctx, msg, params)?;
@@ -195,65 +534,123 @@ impl FlappyBird {
     }
 
     fn draw_bird(&mut self, ctx: &mut Context) -> GameResult {
-        let pos = translate_coords(self.player.pos, self.screen_width, self.screen_height);
-        let image = if self.state.is_playing() {
-            self.assets.player_image(&self.player, self.frames % 15)
-        } else {
-            &mut self.assets.player.player_midflap
-        };
-        let drawparams = DrawParam::new()
-            .dest(pos)
-            .rotation(self.player.facing)
-            .offset(Point2::new(0.5, 0.5));
-        graphics::draw(ctx, image, drawparams)
+        let player = &self.world.player;
+        let pos = self.world.player_screen_pos();
+        draw_player(
+            ctx,
+            &mut self.assets.player,
+            &self.world,
+            pos,
+            graphics::Color::new(1., 1., 1., player.alpha()),
+        )?;
+
+        if player.effects.shield > 0 {
+            let ring = graphics::Mesh::new_circle(
+                ctx,
+                graphics::DrawMode::stroke(2.),
+                pos,
+                player.bbox_size().x * 1.6,
+                0.5,
+                powerup_color(PowerUpKind::Shield),
+            )?;
+            graphics::draw(ctx, &ring, DrawParam::new())?;
+        }
+        Ok(())
     }
 
-    fn clear_pipes(&mut self) {
-        self.pipes.retain(|s| s.0.pos.x > 0.);
+    /// Every bird in the flock in one of the bird colours, the dead ones
+    /// faded out and left behind where they fell
+    fn draw_flock(&mut self, ctx: &mut Context) -> GameResult {
+        let flock = match self.flock {
+            Some(ref flock) => flock,
+            None => return Ok(()),
+        };
+        let lead = flock.lead();
+        let colors = self.assets.flock.len();
+        // the living are drawn last, over the dead
+        let mut order: Vec<_> = flock.worlds.iter().enumerate().collect();
+        order.sort_by_key(|(_, world)| !world.state.is_gameover());
+        for (i, world) in order {
+            let mut pos = world.player_screen_pos();
+            pos.x -= Flock::behind(lead, world);
+            if pos.x < -world.player.bbox_size.x * 2. {
+                continue;
+            }
+            let alpha = if world.state.is_gameover() {
+                0.3
+            } else {
+                world.player.alpha()
+            };
+            let images = &mut self.assets.flock[i % colors];
+            draw_player(ctx, images, world, pos, bird_tint(i, colors, alpha))?;
+        }
+        Ok(())
     }
 
-    fn handle_collisions(&mut self) {
-        let mut player_pos =
-            translate_coords(self.player.pos, self.screen_width, self.screen_height);
-        player_pos.x -= self.offset;
+    /// What the brain sees, as lines from the bird to the edges of the next
+    /// gap, and in the corner the network lit up by what it makes of it
+    fn draw_brain(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let brain = match self.brain {
+            Some(ref brain) => brain,
+            None => return Ok(()),
+        };
+        let inputs = Brain::inputs(&self.world);
+        let values = brain.think(&inputs);
 
-        let player_right = player_pos.x + self.player.bbox_size.x;
-        let player_top = player_pos.y - self.player.bbox_size.y;
-        let player_bottom = player_pos.y + self.player.bbox_size.y;
+        let obs = Observation::of(&self.world);
+        let bird = self.world.player_pos() + Vector2::new(self.world.offset, 0.);
+        let pipe_x = bird.x + obs.pipe_distance;
+        let sight = graphics::Color::new(1., 1., 1., 0.6);
+        let mut mesh = graphics::MeshBuilder::new();
+        mesh.line(&[bird, Point2::new(pipe_x, obs.gap_top)], 2., sight)?;
+        mesh.line(&[bird, Point2::new(pipe_x, obs.gap_bottom)], 2., sight)?;
 
-        if player_bottom >= f32::from(self.assets.bg.bg_h) {
-            self.update_state(GameState::GameOver);
-            return;
+        // a column of nodes for each layer, room for the labels either side
+        let panel = Rect::new(self.world.dims.screen_width - 340., 50., 320., 180.);
+        mesh.rectangle(
+            graphics::DrawMode::fill(),
+            panel,
+            graphics::Color::new(0., 0., 0., 0.6),
+        );
+        let node = |layer: usize, i: usize| {
+            let across = layer as f32 / (values.len() - 1) as f32;
+            let down = (i as f32 + 0.5) / values[layer].len() as f32;
+            Point2::new(
+                panel.x + 120. + across * (panel.w - 170.),
+                panel.y + down * panel.h,
+            )
+        };
+        for layer in 1..values.len() {
+            for to in 0..values[layer].len() {
+                for from in 0..values[layer - 1].len() {
+                    let weight = brain.weight(layer, to, from);
+                    let points = [node(layer - 1, from), node(layer, to)];
+                    mesh.line(&points, 1. + weight.abs().min(2.), weight_color(weight))?;
+                }
+            }
         }
-
-        let is_hit = |pipe: &Pipe| {
-            let top = pipe.facing != 0.;
-            let pipe_right = pipe.pos.x + pipe.bbox_size.x;
-            let pipe_left = pipe.pos.x - pipe.bbox_size.x;
-            let pipe_top = pipe.pos.y - pipe.bbox_size.y;
-            let pipe_bottom = pipe.pos.y + pipe.bbox_size.y;
-
-            let crosses_left = player_right >= pipe_left && player_right <= pipe_right;
-            if crosses_left
-                && (((top && player_top <= pipe_bottom) || (!top && player_bottom >= pipe_top))
-                    || player_bottom <= 0.)
-            {
-                return GameState::GameOver;
+        for (layer, column) in values.iter().enumerate() {
+            for (i, &value) in column.iter().enumerate() {
+                let color = activation_color(value);
+                mesh.circle(graphics::DrawMode::fill(), node(layer, i), 8., 0.5, color);
             }
-            GameState::Playing
-        };
-        let half_width = self.screen_width / 2.;
-        let start = player_pos.x - half_width;
-        let end = player_pos.x + half_width;
-        self.update_state(
-            self.pipes
-                .iter()
-                .filter(|(b, _t)| start <= b.pos.x && b.pos.x <= end)
-                .fold(self.state, |state, (btm, top)| match state {
-                    GameState::GameOver => GameState::GameOver,
-                    _ => is_hit(top) | is_hit(btm),
-                }),
-        );
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::new())?;
+
+        for (i, (name, value)) in Brain::INPUTS.iter().zip(&inputs).enumerate() {
+            let label = format!("{} {:+.2}", name, value);
+            let text = graphics::Text::new((label, self.assets.font, 12.));
+            let (w, h) = text.dimensions(ctx);
+            let pos = node(0, i);
+            let dest = Point2::new(pos.x - 14. - w as f32, pos.y - h as f32 / 2.);
+            graphics::draw(ctx, &text, (dest, 0., graphics::WHITE))?;
+        }
+        let out = node(values.len() - 1, 0);
+        let text = graphics::Text::new(("flap", self.assets.font, 12.));
+        let dest = Point2::new(out.x + 14., out.y - text.height(ctx) as f32 / 2.);
+        graphics::draw(ctx, &text, (dest, 0., graphics::WHITE))?;
+        Ok(())
     }
 
     fn draw_game_over(&mut self, ctx: &mut Context) -> GameResult<()> {
@@ -261,8 +658,8 @@ impl FlappyBird {
         let params = DrawParam::new()
             .dest(translate_coords(
                 Point2::origin(),
-                self.screen_width,
-                self.screen_height - (f32::from(msg.height()) / 2.),
+                self.world.dims.screen_width,
+                self.world.dims.screen_height - (f32::from(msg.height()) / 2.),
             ))
             .offset(Point2::new(0.5, 0.5));
         graphics::draw(ctx, msg, params)?;
@@ -270,50 +667,235 @@ impl FlappyBird {
         Ok(())
     }
 
-    fn count_points(&mut self) {
-        let player_x = translate_coords(self.player.pos, self.screen_width, self.screen_height).x
-            - self.offset;
-        self.score = self
-            .pipes
-            .iter()
-            .filter(|(ref b, _)| b.pos.x < player_x)
-            .count();
+    fn draw_highscores(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let msg = &self.assets.gameover;
+        let center = translate_coords(
+            Point2::origin(),
+            self.world.dims.screen_width,
+            self.world.dims.screen_height - (f32::from(msg.height()) / 2.),
+        );
+        // table goes to the right of gameover.png, name prompt below it
+        let mut dest = Point2::new(
+            center.x + f32::from(msg.width()) / 2. + 30.,
+            center.y - f32::from(msg.height()) / 2.,
+        );
+
+        let title = graphics::Text::new(("High Scores", self.assets.font, 20.));
+        graphics::draw(ctx, &title, (dest, 0., graphics::WHITE))?;
+        dest.y += 28.;
+        for (i, e) in self.highscores.entries().iter().enumerate() {
+            let line = format!(
+                "{:>2}. {:<12} {:>4}  L{:<2} {}  #{}",
+                i + 1,
+                e.name,
+                e.score,
+                e.level,
+                e.date_str(),
+                e.seed
+            );
+            let text = graphics::Text::new((line, self.assets.font, 14.));
+            graphics::draw(ctx, &text, (dest, 0., graphics::WHITE))?;
+            dest.y += 18.;
+        }
+
+        if let Some(ref name) = self.name_entry {
+            let prompt = graphics::Text::new((
+                format!("New high score! Enter your name: {}_", name),
+                self.assets.font,
+                20.,
+            ));
+            let w = prompt.width(ctx) as f32;
+            let dest = Point2::new(center.x - w / 2., center.y + f32::from(msg.height()));
+            graphics::draw(ctx, &prompt, (dest, 0., graphics::WHITE))?;
+        }
+        Ok(())
     }
 
-    fn draw_countdown(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let center = translate_coords(Point2::origin(), self.screen_width, self.screen_height);
-        match self.assets.countdown(self.frames % 183) {
-            Some(num) => graphics::draw(ctx, num, DrawParam::new().dest(center))?,
-            None => self.state = GameState::Playing,
+    /// who won the race, across the top of the game over screen
+    fn draw_winner(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let flock = match self.flock {
+            Some(ref flock) => flock,
+            None => return Ok(()),
+        };
+        let text = graphics::Text::new((race_result(flock), self.assets.font, 40.));
+        let (w, h) = text.dimensions(ctx);
+        let center = translate_coords(
+            Point2::new(0., 120.),
+            self.world.dims.screen_width,
+            self.world.dims.screen_height,
+        );
+        let dest = Point2::new(center.x - w as f32 / 2., center.y - h as f32 / 2.);
+        graphics::draw(ctx, &text, (dest, 0., graphics::WHITE))
+    }
+
+    fn draw_level_up(&mut self, ctx: &mut Context) -> GameResult<()> {
+        // fade out over the last half of the banner
+        let alpha = (self.world.level_up as f32 / (World::LEVEL_UP_TICKS as f32 / 2.)).min(1.);
+        let text = graphics::Text::new((
+            format!("Level {}!", self.world.level + 1),
+            self.assets.font,
+            40.,
+        ));
+        let (w, h) = text.dimensions(ctx);
+        let center = translate_coords(
+            Point2::new(0., 120.),
+            self.world.dims.screen_width,
+            self.world.dims.screen_height,
+        );
+        let dest = Point2::new(center.x - w as f32 / 2., center.y - h as f32 / 2.);
+        graphics::draw(
+            ctx,
+            &text,
+            (dest, 0., graphics::Color::new(1., 1., 1., alpha)),
+        )
+    }
+
+    fn draw_notice(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let mut dest = Point2::new(10., self.world.dims.screen_height - 10.);
+        for line in self.notice.iter().rev() {
+            let text = graphics::Text::new((line.as_str(), self.assets.font, 14.));
+            dest.y -= text.height(ctx) as f32 + 2.;
+            graphics::draw(ctx, &text, (dest, 0., graphics::WHITE))?;
         }
         Ok(())
     }
 
-    fn update_state(&mut self, new_state: GameState) {
-        if self.state.is_paused() && new_state.is_playing() {
-            self.frames = 0;
-            self.state = GameState::Countdown;
-        } else {
-            self.state = new_state;
+    fn draw_controls(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let menu = match self.controls {
+            Some(ref menu) => menu,
+            None => return Ok(()),
+        };
+        let dims = self.world.dims;
+        let shade = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(0., 0., dims.screen_width, dims.screen_height),
+            graphics::Color::new(0., 0., 0., 0.8),
+        )?;
+        graphics::draw(ctx, &shade, DrawParam::new())?;
+
+        let mut dest = Point2::new(dims.screen_width / 4., 60.);
+        let title = graphics::Text::new(("Controls", self.assets.font, 28.));
+        graphics::draw(ctx, &title, (dest, 0., graphics::WHITE))?;
+        dest.y += 44.;
+        for (i, &action) in Action::ALL.iter().enumerate() {
+            let selected = i == menu.selected;
+            let triggers = if selected && menu.waiting {
+                "press a key or button...".to_string()
+            } else {
+                let triggers: Vec<_> = self
+                    .bindings
+                    .triggers(action)
+                    .map(|t| t.to_string())
+                    .collect();
+                triggers.join(", ")
+            };
+            let line = format!(
+                "{} {:<12} {}",
+                if selected { ">" } else { " " },
+                action.name(),
+                triggers
+            );
+            let color = if selected {
+                graphics::Color::new(1., 0.85, 0.2, 1.)
+            } else {
+                graphics::WHITE
+            };
+            let text = graphics::Text::new((line, self.assets.font, 18.));
+            graphics::draw(ctx, &text, (dest, 0., color))?;
+            dest.y += 26.;
+        }
+
+        dest.y += 20.;
+        let help = graphics::Text::new((
+            "<Up>/<Down> choose, <Return> add a binding, <Back> clear, <Escape> done",
+            self.assets.font,
+            14.,
+        ));
+        graphics::draw(ctx, &help, (dest, 0., graphics::WHITE))?;
+        Ok(())
+    }
+
+    fn draw_countdown(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let center = translate_coords(
+            Point2::origin(),
+            self.world.dims.screen_width,
+            self.world.dims.screen_height,
+        );
+        if let Some(num) = self.assets.countdown(self.world.frames) {
+            graphics::draw(ctx, num, DrawParam::new().dest(center))?;
         }
+        Ok(())
     }
 }
 
 impl EventHandler for FlappyBird {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
+        // edits wait for the run to end, the file stays changed until then
+        if !self.rules_locked() {
+            if let Some(result) = self.watcher.as_mut().and_then(ConfigWatcher::poll) {
+                self.reload_config(result);
+            }
+        }
+
         while timer::check_update_time(ctx, DESIRED_FPS) {
-            if self.state.is_playing() {
-                let seconds = 1. / (crate::DESIRED_FPS as f32);
-                self.flap_timeout -= seconds;
-                if self.input.flap && self.flap_timeout < 0. {
-                    self.flap_timeout = crate::FLAP_TIMEOUT;
-                    self.player.flap(seconds);
+            let seconds = 1. / (DESIRED_FPS as f32);
+            self.notice_ticks = self.notice_ticks.saturating_sub(1);
+            if let Some(ref mut playback) = self.playback {
+                self.input = playback.input(self.world.ticks);
+            } else if let Some(ref mut controller) = self.controller {
+                self.input = controller.input(&self.world);
+            }
+            if self.world.state.is_playing() {
+                self.recorder.record(self.world.ticks, self.input);
+            }
+
+            let was_over = self.world.state.is_gameover();
+            let events: Vec<_> = match self.flock {
+                // the flock flies while the game runs, and the course is
+                // the lead bird's
+                Some(ref mut flock)
+                    if !self.world.state.is_paused() && !self.world.state.is_countdown() =>
+                {
+                    let inputs: Vec<_> = if self.args.race {
+                        vec![self.input, self.input_two]
+                    } else {
+                        flock
+                            .worlds
+                            .iter()
+                            .zip(&mut self.pilots)
+                            .map(|(world, pilot)| pilot.input(world))
+                            .collect()
+                    };
+                    flock.step(seconds, &inputs);
+                    self.world.clone_from(flock.lead());
+                    // both racers are heard, a flock only through its lead
+                    let heard = if self.args.race {
+                        &flock.worlds[..]
+                    } else {
+                        std::slice::from_ref(flock.lead())
+                    };
+                    heard
+                        .iter()
+                        .flat_map(|w| w.events.iter().copied())
+                        .collect()
+                }
+                _ => {
+                    self.world.step(seconds, self.input);
+                    self.world.events.clone()
+                }
+            };
+            for event in events {
+                self.audio.play(event, self.world.dims.screen_width)?;
+            }
+            if !was_over && self.world.state.is_gameover() {
+                self.game_over(ctx)?;
+            }
+            if self.restart_ticks > 0 {
+                self.restart_ticks -= 1;
+                if self.restart_ticks == 0 {
+                    self.restart(ctx)?;
                 }
-                self.offset -= crate::MOVE_SPEED;
-                self.frames += 1;
-                self.player.update_pos(seconds);
-            } else if self.state.is_countdown() {
-                self.frames += 1;
             }
         }
 
@@ -323,22 +905,29 @@ impl EventHandler for FlappyBird {
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         graphics::clear(ctx, graphics::WHITE);
         self.draw_bg(ctx)?;
-        self.draw_pipes(ctx)?;
+        self.draw_entities(ctx)?;
         self.draw_base(ctx)?;
 
-        self.draw_bird(ctx)?;
+        if self.flock.is_some() {
+            self.draw_flock(ctx)?;
+        } else {
+            self.draw_bird(ctx)?;
+        }
+        self.draw_brain(ctx)?;
 
-        match self.state {
+        match self.world.state {
             GameState::Paused => {
                 self.draw_menu(ctx)?;
             }
-            GameState::GameOver => {
+            GameState::GameOver if self.args.race => {
                 self.draw_game_over(ctx)?;
+                self.draw_winner(ctx)?;
             }
-            GameState::Playing => {
-                self.handle_collisions();
-                self.count_points();
+            GameState::GameOver => {
+                self.draw_game_over(ctx)?;
+                self.draw_highscores(ctx)?;
             }
+            GameState::Playing => (),
             GameState::Countdown => {
                 self.draw_countdown(ctx)?;
             }
@@ -346,7 +935,17 @@ impl EventHandler for FlappyBird {
 
         self.draw_score(ctx)?;
         self.draw_level(ctx)?;
-        self.clear_pipes();
+        self.draw_effects(ctx)?;
+        self.draw_survivors(ctx)?;
+        if self.world.level_up > 0 {
+            self.draw_level_up(ctx)?;
+        }
+        if self.notice_ticks > 0 {
+            self.draw_notice(ctx)?;
+        }
+        if self.controls.is_some() {
+            self.draw_controls(ctx)?;
+        }
 
         graphics::present(ctx)?;
         // And yield the timeslice
@@ -364,76 +963,94 @@ impl EventHandler for FlappyBird {
         ctx: &mut Context,
         keycode: KeyCode,
         _keymod: KeyMods,
-        _repeat: bool,
+        repeat: bool,
     ) {
-        match keycode {
-            KeyCode::A => {
-                if self.state.is_paused() {
-                    self.update_state(GameState::Playing);
-                }
-                self.input.flap = true;
-            }
-            KeyCode::P => {
-                let img = graphics::screenshot(ctx).expect("Could not take screenshot");
-                img.encode(ctx, graphics::ImageFormat::Png, "/screenshot.png")
-                    .expect("Could not save screenshot");
-            }
-            KeyCode::R => {
-                if self.state.is_gameover() {
-                    self.restart(ctx).expect("Restart failed");
+        // a held key shouldn't bind itself as soon as the menu asks for one
+        if repeat && self.controls.is_some() {
+            return;
+        }
+        if let Some(ref mut name) = self.name_entry {
+            match keycode {
+                KeyCode::Return => self.submit_name(ctx).expect("Could not save high scores"),
+                KeyCode::Back => {
+                    name.pop();
                 }
+                KeyCode::Escape => self.name_entry = None,
+                _ => (),
             }
-            KeyCode::Return => {
-                self.state.toggle_pause();
+            return;
+        }
+        self.trigger_down(ctx, Trigger::Key(keycode));
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        if let Some(ref mut name) = self.name_entry {
+            if (character.is_alphanumeric() || character == ' ') && name.chars().count() < 12 {
+                name.push(character);
             }
-            KeyCode::Escape => ggez::quit(ctx),
-            _ => (),
         }
     }
 
     fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods) {
-        if let KeyCode::A = keycode {
-            self.input.flap = false;
-            // variable height flap
-            // let dir = vec_from_angle(0.);
-            // let flap_vec = dir * (crate::FLAP_SPEED / 2.0);
-            // if self.player.velocity < flap_vec {
-            //     self.player.velocity = flap_vec;
-            // }
-        }
+        self.trigger_up(Trigger::Key(keycode));
     }
 
     fn mouse_button_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         button: MouseButton,
         _x: f32,
         _y: f32,
     ) {
-        if let MouseButton::Left = button {
-            if self.state.is_paused() {
-                self.update_state(GameState::Playing);
-            }
-            self.input.flap = true;
-        }
+        self.trigger_down(ctx, Trigger::Mouse(button));
     }
+
     fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
-        if let MouseButton::Left = button {
-            self.input.flap = false;
+        self.trigger_up(Trigger::Mouse(button));
+    }
+}
+
+impl InputHandler for FlappyBird {
+    fn touch_event(&mut self, ctx: &mut Context, phase: TouchPhase, _x: f32, _y: f32) {
+        match phase {
+            TouchPhase::Started => self.trigger_down(ctx, Trigger::Touch),
+            TouchPhase::Ended | TouchPhase::Cancelled => self.trigger_up(Trigger::Touch),
+            TouchPhase::Moved => (),
+        }
+    }
+
+    fn pad_button_event(&mut self, ctx: &mut Context, button: Button, pressed: bool) {
+        if pressed {
+            self.trigger_down(ctx, Trigger::Pad(button));
+        } else {
+            self.trigger_up(Trigger::Pad(button));
         }
     }
 }
 
-fn print_instructions() {
+fn print_instructions(bindings: &Bindings) {
     println!("{:-^60}", "Welcome to Flappy Bird!");
     println!();
     println!("How to play:");
-    println!("{: <40}", "<a> to flap -- avoid the pipes!");
-    println!("{: <40}", "<r> to restart");
+    for &action in Action::ALL.iter() {
+        let triggers: Vec<_> = bindings
+            .triggers(action)
+            .map(|t| format!("<{}>", t))
+            .collect();
+        if !triggers.is_empty() {
+            println!("{} to {}", triggers.join(", "), action.description());
+        }
+    }
     println!();
 }
 
 pub fn main() -> GameResult {
+    let args = Args::parse(env::args().skip(1))?;
+    let config = match args.config {
+        Some(ref path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
     let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
         path.push("resources");
@@ -444,24 +1061,43 @@ pub fn main() -> GameResult {
 
     let cb = ContextBuilder::new("flappy_bird", "cameron.evan@gmail.com")
         .window_setup(conf::WindowSetup::default().title("Flappy Bird!"))
-        .window_mode(conf::WindowMode::default().dimensions(SCREEN_WIDTH, SCREEN_HEIGHT))
+        .window_mode(
+            conf::WindowMode::default().dimensions(config.screen.width, config.screen.height),
+        )
         .add_resource_path(resource_dir);
 
     let (ctx, events_loop) = &mut cb.build()?;
 
-    let game = &mut FlappyBird::new(ctx)?;
-    event::run(ctx, events_loop, game)
+    let game = &mut FlappyBird::new(ctx, args, config, AudioSettings::load(ctx)?)?;
+    events::run(ctx, events_loop, game)
 }
 
 trait DrawHUD {
     fn draw_score(&self, ctx: &mut Context) -> GameResult<()>;
     fn draw_level(&self, ctx: &mut Context) -> GameResult<()>;
+    fn draw_effects(&self, ctx: &mut Context) -> GameResult<()>;
+    fn draw_survivors(&self, ctx: &mut Context) -> GameResult<()>;
 }
 
 impl DrawHUD for FlappyBird {
     fn draw_score(&self, ctx: &mut Context) -> GameResult<()> {
+        if let (true, Some(flock)) = (self.args.race, &self.flock) {
+            // a score each, faded once that bird is out
+            for (i, world) in flock.worlds.iter().enumerate() {
+                let dest = Point2::new(10., 10. + 26. * i as f32);
+                let line = format!("P{}: {}", i + 1, world.score);
+                let alpha = if world.state.is_gameover() { 0.5 } else { 1. };
+                let text = graphics::Text::new((line, self.assets.font, 20.));
+                graphics::draw(
+                    ctx,
+                    &text,
+                    (dest, 0., graphics::Color::new(1., 1., 1., alpha)),
+                )?;
+            }
+            return Ok(());
+        }
         let score_dest = Point2::new(10., 10.);
-        let score_str = format!("Score: {}", self.score);
+        let score_str = format!("Score: {}", self.world.score);
 
         let score_display = graphics::Text::new((score_str, self.assets.font, 20.));
         graphics::draw(ctx, &score_display, (score_dest, 0., graphics::WHITE))?;
@@ -471,10 +1107,119 @@ impl DrawHUD for FlappyBird {
 
     fn draw_level(&self, ctx: &mut Context) -> GameResult<()> {
         let level_dest = Point2::new(100., 10.);
-        let level_str = format!("Level: {}", self.level);
+        let level_str = format!("Level: {}", self.world.level + 1);
         let level_display = graphics::Text::new((level_str, self.assets.font, 20.));
 
         graphics::draw(ctx, &level_display, (level_dest, 0., graphics::WHITE))?;
         Ok(())
     }
+
+    fn draw_effects(&self, ctx: &mut Context) -> GameResult<()> {
+        // in a race, each bird's power-ups on the row of its score
+        let players = match (self.args.race, &self.flock) {
+            (true, Some(flock)) => &flock.worlds[..],
+            _ => std::slice::from_ref(&self.world),
+        };
+        for (i, world) in players.iter().enumerate() {
+            let mut dest = Point2::new(200., 10. + 26. * i as f32);
+            for (kind, ticks) in world.player.effects.active() {
+                let timer = format!("{} {:.1}s", kind, ticks as f32 / DESIRED_FPS as f32);
+                let text = graphics::Text::new((timer, self.assets.font, 20.));
+                graphics::draw(ctx, &text, (dest, 0., powerup_color(kind)))?;
+                dest.x += text.width(ctx) as f32 + 16.;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_survivors(&self, ctx: &mut Context) -> GameResult<()> {
+        let flock = match self.flock {
+            Some(ref flock) if !self.args.race => flock,
+            _ => return Ok(()),
+        };
+        let dest = Point2::new(10., 36.);
+        let alive = format!("Alive: {}/{}", flock.alive(), flock.worlds.len());
+        let text = graphics::Text::new((alive, self.assets.font, 20.));
+        graphics::draw(ctx, &text, (dest, 0., graphics::WHITE))
+    }
+}
+
+fn powerup_color(kind: PowerUpKind) -> graphics::Color {
+    match kind {
+        PowerUpKind::Shield => graphics::Color::new(0.3, 0.6, 1., 1.),
+        PowerUpKind::SlowMo => graphics::Color::new(0.3, 1., 0.8, 1.),
+        PowerUpKind::Ghost => graphics::Color::new(0.9, 0.9, 0.9, 1.),
+        PowerUpKind::Multiplier => graphics::Color::new(1., 0.85, 0.2, 1.),
+    }
+}
+
+fn race_result(flock: &Flock) -> String {
+    match flock.winner() {
+        Some(i) => format!("Player {} wins!", i + 1),
+        None => "It's a draw!".to_string(),
+    }
+}
+
+/// the bird in `world` at `pos` on screen tinted `color`, flapping while
+/// it's in play
+fn draw_player(
+    ctx: &mut Context,
+    images: &mut PlayerAssets,
+    world: &World,
+    pos: Point2<f32>,
+    color: graphics::Color,
+) -> GameResult {
+    let player = &world.player;
+    let image = if world.state.is_playing() {
+        images.image(player, world.frames % 15)
+    } else {
+        &mut images.player_midflap
+    };
+    let drawparams = DrawParam::new()
+        .dest(pos)
+        .rotation(player.facing())
+        .offset(Point2::new(0.5, 0.5))
+        .color(color);
+    graphics::draw(ctx, image, drawparams)
+}
+
+/// The tint for bird `i` of a flock drawn in `colors` sprite colours. The
+/// first round of birds keep their colours, each round after is tinted a
+/// shade of its own.
+fn bird_tint(i: usize, colors: usize, alpha: f32) -> graphics::Color {
+    let round = i / colors;
+    if round == 0 {
+        return graphics::Color::new(1., 1., 1., alpha);
+    }
+    // hues a golden ratio apart never come back round to the same one
+    let hue = (round as f32 * 0.618_034).fract() * 6.;
+    let channel = |offset: f32| ((hue - offset).abs() - 1.).clamp(0., 1.);
+    let (r, g, b) = (channel(3.), 1. - channel(2.), 1. - channel(4.));
+    // halfway to white, so the sprite's own colour still shows through
+    graphics::Color::new(0.5 + r / 2., 0.5 + g / 2., 0.5 + b / 2., alpha)
+}
+
+// green for weights that excite, red for ones that hold back
+fn weight_color(weight: f32) -> graphics::Color {
+    let alpha = (weight.abs() / 2.).min(1.) * 0.8;
+    if weight > 0. {
+        graphics::Color::new(0.3, 0.9, 0.3, alpha)
+    } else {
+        graphics::Color::new(0.9, 0.3, 0.3, alpha)
+    }
+}
+
+// orange for nodes firing, blue for nodes held down, grey between
+fn activation_color(value: f32) -> graphics::Color {
+    let v = value.clamp(-1., 1.) / 2.;
+    graphics::Color::new(0.5 + v, 0.5 + v * 0.7, 0.5 - v, 1.)
+}
+
+fn powerup_label(kind: PowerUpKind) -> &'static str {
+    match kind {
+        PowerUpKind::Shield => "S",
+        PowerUpKind::SlowMo => "T",
+        PowerUpKind::Ghost => "G",
+        PowerUpKind::Multiplier => "x2",
+    }
 }
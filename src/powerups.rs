@@ -0,0 +1,298 @@
+use crate::{
+    actors::Actor,
+    collision::{Aabb, Shape},
+    entity::{ShapeParams, Sprite},
+};
+
+use nalgebra::Point2;
+use rand::Rng;
+
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PowerUpKind {
+    /// absorbs one pipe hit
+    Shield,
+    /// runs the course and the bird at half speed
+    SlowMo,
+    /// fly through pipes
+    Ghost,
+    /// doubles points scored
+    Multiplier,
+}
+
+impl PowerUpKind {
+    /// how long one pickup lasts, in ticks
+    pub fn duration(self) -> u32 {
+        match self {
+            PowerUpKind::Shield => 10 * crate::DESIRED_FPS,
+            PowerUpKind::SlowMo => 5 * crate::DESIRED_FPS,
+            PowerUpKind::Ghost => 4 * crate::DESIRED_FPS,
+            PowerUpKind::Multiplier => 10 * crate::DESIRED_FPS,
+        }
+    }
+
+    fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        match rng.gen_range(0, 4) {
+            0 => PowerUpKind::Shield,
+            1 => PowerUpKind::SlowMo,
+            2 => PowerUpKind::Ghost,
+            _ => PowerUpKind::Multiplier,
+        }
+    }
+}
+
+impl fmt::Display for PowerUpKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::SlowMo => "Slow-mo",
+            PowerUpKind::Ghost => "Ghost",
+            PowerUpKind::Multiplier => "x2",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A power-up waiting in a pipe gap, in pipe space like the pipes
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerUp {
+    pub pos: Point2<f32>,
+    pub kind: PowerUpKind,
+    pub bbox_size: Point2<f32>,
+}
+
+impl PowerUp {
+    pub const POWERUP_CHANCE: f32 = 0.1;
+
+    pub fn new<R>(pos: Point2<f32>, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        PowerUp {
+            pos,
+            kind: PowerUpKind::random(rng),
+            bbox_size: Point2::new(12., 12.),
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.pos, self.bbox_size.coords)
+    }
+}
+
+impl Actor for PowerUp {
+    fn pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    fn bbox_size(&self) -> Point2<f32> {
+        self.bbox_size
+    }
+
+    fn sprite(&self) -> Sprite {
+        Sprite::PowerUp(self.kind)
+    }
+
+    fn shape<'a>(&self, _params: ShapeParams<'a>) -> Option<Shape<'a>> {
+        Some(Shape::Aabb(self.aabb()))
+    }
+
+    fn update(&mut self, _dt: f32) {}
+}
+
+/// Power-ups active on the player, as ticks left on each. Picking up one
+/// that's already running adds to its time, up to `MAX_STACK` pickups'
+/// worth. The shield only ever holds one hit.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd)]
+pub struct Effects {
+    pub shield: u32,
+    pub slow_mo: u32,
+    pub ghost: u32,
+    pub multiplier: u32,
+}
+
+impl Effects {
+    const MAX_STACK: u32 = 3;
+    /// ticks of ghost after the shield takes a hit, to get clear of the pipe
+    const SHIELD_GRACE: u32 = crate::DESIRED_FPS;
+    const SLOW_MO_SCALE: f32 = 0.5;
+    const MULTIPLIER: usize = 2;
+
+    const KINDS: [PowerUpKind; 4] = [
+        PowerUpKind::Shield,
+        PowerUpKind::SlowMo,
+        PowerUpKind::Ghost,
+        PowerUpKind::Multiplier,
+    ];
+
+    pub fn add(&mut self, kind: PowerUpKind) {
+        let duration = kind.duration();
+        let timer = self.timer_mut(kind);
+        *timer = match kind {
+            PowerUpKind::Shield => duration,
+            _ => (*timer + duration).min(duration * Effects::MAX_STACK),
+        };
+    }
+
+    /// count every effect down by a tick
+    pub fn tick(&mut self) {
+        for &kind in Effects::KINDS.iter() {
+            let timer = self.timer_mut(kind);
+            *timer = timer.saturating_sub(1);
+        }
+    }
+
+    /// Try to survive a pipe hit. Ghosts pass straight through, a shield is
+    /// used up and leaves a moment of ghost to fly out of the pipe.
+    pub fn absorb_hit(&mut self) -> bool {
+        if self.ghost > 0 {
+            true
+        } else if self.shield > 0 {
+            self.shield = 0;
+            self.ghost = Effects::SHIELD_GRACE;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// how fast the course and the bird run
+    pub fn time_scale(&self) -> f32 {
+        if self.slow_mo > 0 {
+            Effects::SLOW_MO_SCALE
+        } else {
+            1.
+        }
+    }
+
+    pub fn score_multiplier(&self) -> usize {
+        if self.multiplier > 0 {
+            Effects::MULTIPLIER
+        } else {
+            1
+        }
+    }
+
+    /// running effects with their ticks left, for the HUD
+    pub fn active(&self) -> impl Iterator<Item = (PowerUpKind, u32)> + '_ {
+        Effects::KINDS
+            .iter()
+            .map(move |&kind| (kind, self.timer(kind)))
+            .filter(|&(_, ticks)| ticks > 0)
+    }
+
+    fn timer(&self, kind: PowerUpKind) -> u32 {
+        match kind {
+            PowerUpKind::Shield => self.shield,
+            PowerUpKind::SlowMo => self.slow_mo,
+            PowerUpKind::Ghost => self.ghost,
+            PowerUpKind::Multiplier => self.multiplier,
+        }
+    }
+
+    fn timer_mut(&mut self, kind: PowerUpKind) -> &mut u32 {
+        match kind {
+            PowerUpKind::Shield => &mut self.shield,
+            PowerUpKind::SlowMo => &mut self.slow_mo,
+            PowerUpKind::Ghost => &mut self.ghost,
+            PowerUpKind::Multiplier => &mut self.multiplier,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pickups_stack_up_to_the_cap() {
+        for &kind in &[
+            PowerUpKind::SlowMo,
+            PowerUpKind::Ghost,
+            PowerUpKind::Multiplier,
+        ] {
+            let mut effects = Effects::default();
+            effects.add(kind);
+            assert_eq!(effects.timer(kind), kind.duration());
+            effects.tick();
+            effects.add(kind);
+            assert_eq!(effects.timer(kind), 2 * kind.duration() - 1);
+            for _ in 0..5 {
+                effects.add(kind);
+            }
+            assert_eq!(effects.timer(kind), Effects::MAX_STACK * kind.duration());
+        }
+
+        // the shield holds one hit however many are picked up
+        let mut effects = Effects::default();
+        effects.add(PowerUpKind::Shield);
+        effects.tick();
+        effects.add(PowerUpKind::Shield);
+        assert_eq!(effects.shield, PowerUpKind::Shield.duration());
+    }
+
+    #[test]
+    fn effects_run_out_a_tick_at_a_time() {
+        let mut effects = Effects::default();
+        effects.add(PowerUpKind::SlowMo);
+        effects.add(PowerUpKind::Multiplier);
+        assert_eq!(effects.time_scale(), Effects::SLOW_MO_SCALE);
+        assert_eq!(effects.score_multiplier(), Effects::MULTIPLIER);
+
+        for _ in 1..PowerUpKind::SlowMo.duration() {
+            effects.tick();
+        }
+        assert_eq!(effects.slow_mo, 1);
+        assert_eq!(effects.time_scale(), Effects::SLOW_MO_SCALE);
+        effects.tick();
+        assert_eq!(effects.slow_mo, 0);
+        assert_eq!(effects.time_scale(), 1.);
+        // the multiplier lasts longer
+        assert_eq!(
+            effects.active().collect::<Vec<_>>(),
+            [(
+                PowerUpKind::Multiplier,
+                PowerUpKind::Multiplier.duration() - PowerUpKind::SlowMo.duration()
+            )]
+        );
+
+        // and nothing counts below zero
+        for _ in 0..PowerUpKind::Multiplier.duration() {
+            effects.tick();
+        }
+        assert_eq!(effects, Effects::default());
+        assert_eq!(effects.score_multiplier(), 1);
+    }
+
+    #[test]
+    fn a_shield_takes_one_hit_then_gives_grace() {
+        let mut effects = Effects::default();
+        assert!(!effects.absorb_hit());
+
+        effects.add(PowerUpKind::Shield);
+        assert!(effects.absorb_hit());
+        assert_eq!(effects.shield, 0);
+        assert_eq!(effects.ghost, Effects::SHIELD_GRACE);
+        // still inside the pipe during the grace period
+        for _ in 1..Effects::SHIELD_GRACE {
+            effects.tick();
+            assert!(effects.absorb_hit());
+        }
+        effects.tick();
+        assert!(!effects.absorb_hit());
+    }
+
+    #[test]
+    fn ghosts_keep_their_shield() {
+        let mut effects = Effects::default();
+        effects.add(PowerUpKind::Shield);
+        effects.add(PowerUpKind::Ghost);
+        assert!(effects.absorb_hit());
+        assert_eq!(effects.shield, PowerUpKind::Shield.duration());
+        assert_eq!(effects.ghost, PowerUpKind::Ghost.duration());
+    }
+}
@@ -0,0 +1,394 @@
+use crate::{actors::FlapModel, config::Config, levels::Levels, seed::GameSeed, world::InputState};
+
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"FBRP";
+/// Bumped whenever the layout or the game rules change, so a replay from
+/// another build is turned away instead of quietly diverging
+const VERSION: u8 = 3;
+
+/// A fingerprint of the settings a run depends on besides its seed and
+/// input: the config, flap model included, and the level curve. FNV-1a over
+/// their toml, so it comes out the same from one build to the next.
+pub fn rules_hash(config: &Config, levels: &Levels) -> u64 {
+    let config = toml::to_string(config).expect("config is valid toml");
+    let levels = toml::to_string(levels).expect("levels are valid toml");
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in config.bytes().chain(levels.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// A recorded run: the seed of the course plus every tick on which the
+/// flap input changed. Flap starts released, so the ticks alternate between
+/// press and release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: GameSeed,
+    /// `rules_hash` of the settings it was played with
+    pub rules: u64,
+    /// kept apart from the hash so playback can switch to it by itself
+    pub flap_model: FlapModel,
+    pub transitions: Vec<u64>,
+    pub score: u64,
+    /// tick the run ended on
+    pub end_tick: u64,
+}
+
+impl Replay {
+    pub fn new(seed: GameSeed, rules: u64, flap_model: FlapModel) -> Self {
+        Replay {
+            seed,
+            rules,
+            flap_model,
+            transitions: Vec::new(),
+            score: 0,
+            end_tick: 0,
+        }
+    }
+
+    /// `config` switched to the flap model the run was played with
+    pub fn config(&self, mut config: Config) -> Config {
+        config.physics.flap_model = self.flap_model;
+        config
+    }
+
+    /// Write the replay out. Transitions are stored as varint encoded deltas
+    /// so a typical run is a few hundred bytes.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        w.write_all(&self.seed.0.to_le_bytes())?;
+        w.write_all(&self.rules.to_le_bytes())?;
+        w.write_all(&[model_byte(self.flap_model)])?;
+        write_varint(w, self.score)?;
+        write_varint(w, self.end_tick)?;
+        write_varint(w, self.transitions.len() as u64)?;
+        let mut last = 0;
+        for &tick in &self.transitions {
+            write_varint(w, tick - last)?;
+            last = tick;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a replay file"));
+        }
+        let mut version = [0; 1];
+        r.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(invalid("replay recorded by another version of the game"));
+        }
+        let mut seed = [0; 8];
+        r.read_exact(&mut seed)?;
+        let seed = GameSeed(u64::from_le_bytes(seed));
+        let mut rules = [0; 8];
+        r.read_exact(&mut rules)?;
+        let rules = u64::from_le_bytes(rules);
+        let mut model = [0; 1];
+        r.read_exact(&mut model)?;
+        let flap_model = byte_model(model[0]).ok_or_else(|| invalid("unknown flap model"))?;
+        let score = read_varint(r)?;
+        let end_tick = read_varint(r)?;
+        let len = read_varint(r)?;
+        let mut transitions = Vec::new();
+        let mut last: u64 = 0;
+        for _ in 0..len {
+            last = last
+                .checked_add(read_varint(r)?)
+                .ok_or_else(|| invalid("tick overflow"))?;
+            transitions.push(last);
+        }
+        Ok(Replay {
+            seed,
+            rules,
+            flap_model,
+            transitions,
+            score,
+            end_tick,
+        })
+    }
+}
+
+/// Builds a `Replay` from the input the world sees each tick
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    replay: Replay,
+    flap: bool,
+}
+
+impl Recorder {
+    pub fn new(seed: GameSeed, rules: u64, flap_model: FlapModel) -> Self {
+        Recorder {
+            replay: Replay::new(seed, rules, flap_model),
+            flap: false,
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, input: InputState) {
+        if input.flap != self.flap {
+            self.flap = input.flap;
+            self.replay.transitions.push(tick);
+        }
+    }
+
+    pub fn finish(&self, score: u64, end_tick: u64) -> Replay {
+        Replay {
+            score,
+            end_tick,
+            ..self.replay.clone()
+        }
+    }
+}
+
+/// Feeds the input of a `Replay` back tick by tick, as long as it was
+/// recorded under the same rules
+#[derive(Debug, Clone)]
+pub struct Playback {
+    pub replay: Replay,
+    next: usize,
+    flap: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay, rules: u64) -> io::Result<Self> {
+        if replay.rules != rules {
+            return Err(invalid(
+                "replay recorded with other settings, check the config, levels and flap model",
+            ));
+        }
+        Ok(Playback {
+            replay,
+            next: 0,
+            flap: false,
+        })
+    }
+
+    /// input for `tick`, ticks must be asked for in order
+    pub fn input(&mut self, tick: u64) -> InputState {
+        while self
+            .replay
+            .transitions
+            .get(self.next)
+            .is_some_and(|&t| t <= tick)
+        {
+            self.flap = !self.flap;
+            self.next += 1;
+        }
+        InputState { flap: self.flap }
+    }
+}
+
+fn write_varint<W: Write>(w: &mut W, mut n: u64) -> io::Result<()> {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        r.read_exact(&mut byte)?;
+        n |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(invalid("varint too long"))
+}
+
+fn model_byte(model: FlapModel) -> u8 {
+    match model {
+        FlapModel::Classic => 0,
+        FlapModel::Variable => 1,
+        FlapModel::Additive => 2,
+    }
+}
+
+fn byte_model(byte: u8) -> Option<FlapModel> {
+    match byte {
+        0 => Some(FlapModel::Classic),
+        1 => Some(FlapModel::Variable),
+        2 => Some(FlapModel::Additive),
+        _ => None,
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        controller::Autopilot,
+        gamestate::GameState,
+        world::{Dimensions, World},
+    };
+
+    fn varint_round_trip(n: u64) -> usize {
+        let mut buf = Vec::new();
+        write_varint(&mut buf, n).unwrap();
+        assert_eq!(read_varint(&mut buf.as_slice()).unwrap(), n);
+        buf.len()
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        assert_eq!(varint_round_trip(0), 1);
+        assert_eq!(varint_round_trip(127), 1);
+        assert_eq!(varint_round_trip(128), 2);
+        assert_eq!(varint_round_trip(300), 2);
+        assert_eq!(varint_round_trip(u64::from(u32::MAX)), 5);
+        assert_eq!(varint_round_trip(u64::MAX), 10);
+        // a continuation bit on every byte never ends
+        let endless = [0xff; 11];
+        assert!(read_varint(&mut &endless[..]).is_err());
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let replay = Replay {
+            seed: GameSeed(42),
+            rules: 7,
+            flap_model: FlapModel::Additive,
+            transitions: vec![3, 9, 200, 201, 100_000],
+            score: 12,
+            end_tick: 100_500,
+        };
+        let mut buf = Vec::new();
+        replay.write(&mut buf).unwrap();
+        assert_eq!(Replay::read(&mut buf.as_slice()).unwrap(), replay);
+
+        // another version is turned away
+        buf[MAGIC.len()] = VERSION + 1;
+        assert!(Replay::read(&mut buf.as_slice()).is_err());
+        assert!(Replay::read(&mut &b"RIFF"[..]).is_err());
+        buf[MAGIC.len()] = VERSION;
+        buf[MAGIC.len() + 17] = 3;
+        assert!(Replay::read(&mut buf.as_slice()).is_err());
+    }
+
+    #[test]
+    fn playback_gives_back_what_was_recorded() {
+        let inputs: Vec<bool> = (0..500u64).map(|t| t % 37 < 5 || t % 101 == 50).collect();
+        let mut recorder = Recorder::new(GameSeed(1), 3, FlapModel::Classic);
+        for (tick, &flap) in inputs.iter().enumerate() {
+            recorder.record(tick as u64, InputState { flap });
+        }
+        let mut buf = Vec::new();
+        recorder.finish(4, 500).write(&mut buf).unwrap();
+        let replay = Replay::read(&mut buf.as_slice()).unwrap();
+
+        assert!(Playback::new(replay.clone(), 4).is_err());
+        let mut playback = Playback::new(replay, 3).unwrap();
+        for (tick, &flap) in inputs.iter().enumerate() {
+            assert_eq!(playback.input(tick as u64).flap, flap, "tick {}", tick);
+        }
+    }
+
+    #[test]
+    fn rules_cover_the_flap_model_and_levels() {
+        let config = Config::default();
+        let levels = Levels::default();
+        let rules = rules_hash(&config, &levels);
+        assert_eq!(rules, rules_hash(&config, &levels));
+
+        let mut other = config;
+        other.physics.flap_model = other.physics.flap_model.next();
+        assert_ne!(rules, rules_hash(&other, &levels));
+
+        let easier = Levels::from_toml(
+            "[[level]]\nscore = 0\ngap = 2.0\nspacing = 1.0\nspeed = 1.0\nmargin = 1.0\n",
+        )
+        .unwrap();
+        assert_ne!(rules, rules_hash(&config, &easier));
+    }
+
+    #[test]
+    fn replays_bring_their_flap_model() {
+        let mut config = Config::default();
+        config.physics.flap_model = FlapModel::Variable;
+        let levels = Levels::default();
+        let replay = Replay::new(
+            GameSeed(1),
+            rules_hash(&config, &levels),
+            config.physics.flap_model,
+        );
+
+        // played back under the default model, the replay's own is put back
+        let config = replay.config(Config::default());
+        assert_eq!(config.physics.flap_model, FlapModel::Variable);
+        assert!(Playback::new(replay, rules_hash(&config, &levels)).is_ok());
+    }
+
+    // a run the way the game plays one: recording while playing, then
+    // stepping, until it's over
+    fn play(
+        world: &mut World,
+        recorder: &mut Recorder,
+        mut input: impl FnMut(&World) -> InputState,
+    ) {
+        world.update_state(GameState::Playing);
+        while !world.state.is_gameover() && world.ticks < 3_000 {
+            let input = input(world);
+            if world.state.is_playing() {
+                recorder.record(world.ticks, input);
+            }
+            world.step(1. / 60., input);
+        }
+    }
+
+    #[test]
+    fn a_flap_model_switched_before_the_run_is_replayed() {
+        let levels = Levels::default();
+        let seed = GameSeed(5);
+        let mut config = Config::default();
+        config.physics.flap_model = FlapModel::Additive;
+        let mut world = World::new(Dimensions::default(), seed, levels.clone(), config);
+
+        // switched before the run, which lays the course out again and
+        // starts the recording over under the new rules, as the game does
+        let mut switched = config;
+        switched.physics.flap_model = FlapModel::Classic;
+        world.apply_config(switched);
+        let mut recorder = Recorder::new(
+            seed,
+            rules_hash(&switched, &levels),
+            switched.physics.flap_model,
+        );
+        play(&mut world, &mut recorder, |world| InputState {
+            flap: Autopilot::aim(world),
+        });
+        let replay = recorder.finish(world.score as u64, world.ticks);
+        assert!(replay.score > 0);
+
+        // played back under the other model, it's the replay's that counts
+        let config = replay.config(config);
+        assert_eq!(config.physics.flap_model, FlapModel::Classic);
+        let mut playback = Playback::new(replay.clone(), rules_hash(&config, &levels)).unwrap();
+        let mut again = World::new(Dimensions::default(), replay.seed, levels, config);
+        play(
+            &mut again,
+            &mut Recorder::new(seed, 0, config.physics.flap_model),
+            |world| playback.input(world.ticks),
+        );
+        assert_eq!(
+            (again.score as u64, again.ticks),
+            (replay.score, replay.end_tick)
+        );
+    }
+}
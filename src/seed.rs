@@ -0,0 +1,54 @@
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::{fmt, num::ParseIntError, str::FromStr};
+
+/// Everything random in a run (the pipe course and which sprites get used)
+/// comes from a `GameSeed`, so a run can be shared and played again.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct GameSeed(pub u64);
+
+impl GameSeed {
+    pub fn random() -> Self {
+        GameSeed(rand::random())
+    }
+
+    /// rng for generating the course
+    pub fn world_rng(self) -> StdRng {
+        StdRng::seed_from_u64(self.0)
+    }
+
+    /// rng for picking sprites, kept apart from the course so a headless
+    /// world sees the same pipes as a windowed one
+    pub fn asset_rng(self) -> StdRng {
+        StdRng::seed_from_u64(!self.0)
+    }
+}
+
+impl fmt::Display for GameSeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for GameSeed {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(GameSeed)
+    }
+}
+
+// seeds use the full u64 range, which toml can't hold, so store them as strings
+impl Serialize for GameSeed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for GameSeed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
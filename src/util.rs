@@ -1,4 +1,9 @@
-use ggez::nalgebra::{Point2, Vector2};
+#[cfg(feature = "window")]
+use ggez::{filesystem, Context, GameResult};
+use nalgebra::{Point2, Vector2};
+
+#[cfg(feature = "window")]
+use std::{fs, path::PathBuf};
 
 pub fn vec_from_angle(angle: f32) -> Vector2<f32> {
     let vx = angle.sin();
@@ -14,4 +19,23 @@ pub fn translate_coords(point: Point2<f32>, screen_width: f32, screen_height: f3
     let x = point.x + screen_width / 2.;
     let y = screen_height - (point.y + screen_height / 2.);
     Point2::new(x, y)
-}
\ No newline at end of file
+}
+
+/// Path to `name` in the user data directory, creating the directory if
+/// needed. ggez only lets us write to the config directory, so files that
+/// belong in the data directory go through `std::fs`.
+#[cfg(feature = "window")]
+pub fn user_data_file(ctx: &Context, name: &str) -> GameResult<PathBuf> {
+    let dir = filesystem::user_data_dir(ctx);
+    fs::create_dir_all(dir)?;
+    Ok(dir.join(name))
+}
+
+/// Path to `name` in the user config directory, creating the directory if
+/// needed
+#[cfg(feature = "window")]
+pub fn user_config_file(ctx: &Context, name: &str) -> GameResult<PathBuf> {
+    let dir = filesystem::user_config_dir(ctx);
+    fs::create_dir_all(dir)?;
+    Ok(dir.join(name))
+}
@@ -0,0 +1,204 @@
+use crate::{
+    controller::Autopilot,
+    entity::Contact,
+    gamestate::GameState,
+    powerups::Effects,
+    world::{InputState, World},
+};
+
+use std::{collections::HashSet, fmt};
+
+/// A pipe pair that no run of the course got past
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unreachable {
+    /// which pair, counting from 0 for the first one in the course
+    pub pipe: usize,
+    /// the furthest tick any run got to
+    pub tick: u64,
+    /// false when the search ran out of budget before trying every way
+    /// through, so the pipe might be reachable after all
+    pub proven: bool,
+}
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pipe {} is {}unreachable, no run got past tick {}",
+            self.pipe,
+            if self.proven { "" } else { "probably " },
+            self.tick
+        )
+    }
+}
+
+/// Searches a course for a way through its first `pipes` pairs, flying
+/// copies of `world` from where it is now. Each tick the bird can flap or
+/// not, the flap the autopilot would make is tried first.
+///
+/// Gives back the flap input for every tick of a way through, or the first
+/// pair that can't be got past. Places the bird has already been stuck in
+/// are skipped, to the nearest half pixel of height and tenth of a pixel
+/// per tick of speed, and the search gives up after playing `budget` ticks.
+pub fn validate(world: &World, pipes: usize, budget: u64) -> Result<Vec<bool>, Unreachable> {
+    let dt = 1. / crate::DESIRED_FPS as f32;
+    let mut root = world.clone();
+    root.state = GameState::Playing;
+    let target = root.passed + pipes;
+
+    // worlds along the current path, each with the moves left to try
+    let mut stack = vec![(moves(&root), root)];
+    let mut path = Vec::new();
+    let mut dead = HashSet::new();
+    let mut played = 0;
+    let mut furthest = (world.passed, world.ticks);
+
+    while let Some((untried, world)) = stack.last_mut() {
+        furthest = furthest.max((world.passed, world.ticks));
+        if world.passed >= target {
+            return Ok(path);
+        }
+        let flap = match untried.pop() {
+            Some(flap) => flap,
+            None => {
+                dead.insert(place(world));
+                stack.pop();
+                path.pop();
+                continue;
+            }
+        };
+        if played >= budget {
+            return Err(Unreachable {
+                pipe: furthest.0,
+                tick: furthest.1,
+                proven: false,
+            });
+        }
+        played += 1;
+
+        let mut next = world.clone();
+        next.step(dt, InputState { flap });
+        if next.state.is_playing() && !dead.contains(&place(&next)) {
+            path.push(flap);
+            stack.push((moves(&next), next));
+        }
+    }
+    Err(Unreachable {
+        pipe: furthest.0,
+        tick: furthest.1,
+        proven: true,
+    })
+}
+
+/// moves worth trying, the last one first
+fn moves(world: &World) -> Vec<bool> {
+    let aim = Autopilot::aim(world);
+    // not flapping is all there is until the timeout runs out
+    if world.flap_timeout - 1. / (crate::DESIRED_FPS as f32) < 0. {
+        vec![!aim, aim]
+    } else {
+        vec![false]
+    }
+}
+
+/// Near enough the same spot, for skipping dead ends. The score is in
+/// there since it sets the level, and the level sets the course ahead. The
+/// flap timeout, which way the bird is turned, what it's picked up and
+/// what's still there to pick up matter as much as where it is.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Place {
+    ticks: u64,
+    score: usize,
+    y: i32,
+    velocity: i32,
+    flap_timeout: i32,
+    facing: i32,
+    effects: Effects,
+    pickups: usize,
+}
+
+fn place(world: &World) -> Place {
+    let player = &world.player;
+    Place {
+        ticks: world.ticks,
+        score: world.score,
+        y: (player.pos.y * 2.).round() as i32,
+        velocity: (player.velocity.y * 10.).round() as i32,
+        flap_timeout: (world.flap_timeout.max(0.) * crate::DESIRED_FPS as f32).round() as i32,
+        facing: (player.facing * 100.).round() as i32,
+        effects: player.effects,
+        pickups: world
+            .entities
+            .iter()
+            .filter(|e| matches!(e.contact(), Some(c) if c != Contact::Solid))
+            .count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::Entity, env::Env, seed::GameSeed};
+
+    use std::ops::Range;
+
+    const BUDGET: u64 = 1_000_000;
+
+    fn start(seed: u64) -> World {
+        let mut env = Env::default();
+        env.reset(GameSeed(seed));
+        env.world().clone()
+    }
+
+    fn finds_a_way_through(seeds: Range<u64>) {
+        for seed in seeds {
+            let world = start(seed);
+            let path =
+                validate(&world, 30, BUDGET).unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+            // the path really flies the course
+            let mut world = world;
+            for &flap in &path {
+                world.step(1. / crate::DESIRED_FPS as f32, InputState { flap });
+            }
+            assert!(world.state.is_playing());
+            assert_eq!(world.passed, 30);
+        }
+    }
+
+    #[test]
+    fn finds_a_way_through_every_course() {
+        finds_a_way_through(0..2);
+    }
+
+    #[test]
+    #[ignore = "slow, run with cargo test --release -- --ignored"]
+    fn finds_a_way_through_many_courses() {
+        finds_a_way_through(0..50);
+    }
+
+    #[test]
+    fn reports_a_closed_gap() {
+        let mut world = start(0);
+        // fly up to the first pair, near enough that the search is short
+        let path = validate(&world, 1, BUDGET).unwrap();
+        for &flap in &path {
+            if world.next_pair().unwrap().0.pos.x - world.player_pos().x < 80. {
+                break;
+            }
+            world.step(1. / crate::DESIRED_FPS as f32, InputState { flap });
+        }
+        // then drop its top pipe down onto the bottom one
+        let bottom = world.next_pair().unwrap().0.clone();
+        for entity in world.entities.iter_mut() {
+            if let Entity::Pipe(pipe) = entity {
+                if pipe.is_top() && pipe.rest.x == bottom.rest.x {
+                    pipe.rest.y = bottom.rest.y - 2. * bottom.bbox_size.y;
+                    pipe.pos = pipe.rest;
+                }
+            }
+        }
+        let err = validate(&world, 5, BUDGET).unwrap_err();
+        assert_eq!(err.pipe, 0);
+        assert!(err.proven);
+    }
+}
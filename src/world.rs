@@ -0,0 +1,623 @@
+use crate::{
+    actors::{Actor, AppleKind, Pipe, PipeSpawner, Player},
+    collision::Masks,
+    config::Config,
+    entity::{Contact, Decoration, Entity, ShapeParams},
+    gamestate::GameState,
+    levels::Levels,
+    powerups::PowerUpKind,
+    seed::GameSeed,
+    util::translate_coords,
+};
+
+use nalgebra::Point2;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct InputState {
+    pub flap: bool,
+}
+
+/// Things that happened during the last tick, with where on screen they
+/// happened, for the renderer to react to
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Event {
+    Flap(Point2<f32>),
+    Point(Point2<f32>),
+    Hit(Point2<f32>),
+    Collect(Point2<f32>, AppleKind),
+    PowerUp(Point2<f32>, PowerUpKind),
+}
+
+/// What happens when the bird flies into the top of the screen
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ceiling {
+    /// stop dead against it
+    Clamp,
+    /// bounce back down, losing half the speed
+    Bounce,
+    /// game over, like hitting a pipe
+    Death,
+}
+
+/// Sizes the simulation needs to know about the playfield. These normally
+/// come from the window and the loaded sprites, but have defaults matching
+/// the bundled resources so a `World` can be built without a `Context`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Dimensions {
+    pub screen_width: f32,
+    pub screen_height: f32,
+    /// height of the background, the base starts here
+    pub ground: f32,
+    /// half the height of a pipe sprite
+    pub pipe_h: f32,
+}
+
+impl Default for Dimensions {
+    fn default() -> Self {
+        Dimensions {
+            screen_width: crate::SCREEN_WIDTH,
+            screen_height: crate::SCREEN_HEIGHT,
+            ground: 512.,
+            pipe_h: 160.,
+        }
+    }
+}
+
+/// The game rules, independent of any window or assets. `FlappyBird`
+/// renders a `World` and feeds it input, but a `World` can just as well be
+/// stepped headless.
+#[derive(Debug, Clone)]
+pub struct World {
+    pub player: Player,
+    /// pipes, pickups and decorations, in the order they spawned
+    pub entities: Vec<Entity>,
+    pub spawner: PipeSpawner,
+    pub levels: Levels,
+    pub level: usize,
+    /// ticks left to show the level up banner for
+    pub level_up: u32,
+    pub config: Config,
+    pub score: usize,
+    // pipes passed and scrolled off screen
+    pub cleared: usize,
+    /// pipes passed so far, scored as they're passed
+    pub passed: usize,
+    /// points from apples, part of score
+    pub bonus: usize,
+    pub dims: Dimensions,
+    pub flap_timeout: f32,
+    /// input from the last tick, to spot the flap being let go of
+    input: InputState,
+    pub offset: f32,
+    pub frames: u64,
+    /// ticks spent playing, the clock replays are recorded against
+    pub ticks: u64,
+    pub state: GameState,
+    pub seed: GameSeed,
+    /// events from the last call to `step`
+    pub events: Vec<Event>,
+    /// sprite masks for `ShapeKind::Mask`, only set when there are sprites.
+    /// Shared so copies of the world for looking ahead stay cheap.
+    pub masks: Option<Arc<Masks>>,
+    rng: StdRng,
+}
+
+impl World {
+    pub const LEVEL_UP_TICKS: u32 = 120;
+    /// ticks of "3, 2, 1" before play starts
+    pub const COUNTDOWN_TICKS: u64 = 180;
+    /// speed kept when bouncing off the ceiling
+    const BOUNCE: f32 = 0.5;
+
+    pub fn new(dims: Dimensions, seed: GameSeed, levels: Levels, config: Config) -> World {
+        let mut rng = seed.world_rng();
+        let mut entities = Vec::new();
+        let mut spawner = PipeSpawner::new(dims, config.pipes, config.physics);
+        spawner.fill(&mut entities, dims, 0., levels.get(0), &mut rng);
+
+        World {
+            player: Player {
+                physics: config.physics,
+                ..Player::new()
+            },
+            entities,
+            spawner,
+            levels,
+            level: 0,
+            level_up: 0,
+            config,
+            score: 0,
+            cleared: 0,
+            passed: 0,
+            bonus: 0,
+            dims,
+            flap_timeout: 0.,
+            input: InputState::default(),
+            offset: 0.,
+            frames: 0,
+            ticks: 0,
+            state: GameState::Paused,
+            seed,
+            events: Vec::new(),
+            masks: None,
+            rng,
+        }
+    }
+
+    /// Advance the world by one fixed tick of `dt` seconds. All of the
+    /// rules run from here, never from drawing, in this order:
+    ///
+    /// 1. countdown, which starts play once it runs out
+    /// 2. input, flapping if the timeout allows it
+    /// 3. movement, scrolling the course, counting power-ups down and
+    ///    updating the player and every entity
+    /// 4. the world bounds, then collisions with the pipes
+    /// 5. scoring for pipes passed and apples collected, power-ups picked
+    ///    up and level progression
+    /// 6. despawning entities that are done with and spawning new pipes
+    ///
+    /// so the same seed and input give the same run at any frame rate.
+    /// Once the game is over the bird falls until it lands on the ground.
+    pub fn step(&mut self, dt: f32, input: InputState) {
+        self.events.clear();
+        if self.state.is_countdown() {
+            self.countdown();
+        } else if self.state.is_playing() {
+            self.apply_input(dt, input);
+            self.move_actors(dt);
+            self.apply_bounds();
+            if self.state.is_playing() {
+                self.handle_collisions();
+            }
+            self.score_points();
+            self.update_pipes();
+        } else if self.state.is_gameover() && !self.landed() {
+            self.land(dt);
+        }
+    }
+
+    /// Swap in new settings mid run. The bird and the pipes spawned from
+    /// here on use them straight away, the screen size can't change.
+    /// Before the first tick the course is laid out again, so it's the one
+    /// a new world with these settings would have and a replay of the run
+    /// starts from the same place.
+    pub fn apply_config(&mut self, config: Config) {
+        let config = Config {
+            screen: self.config.screen,
+            ..config
+        };
+        if self.ticks == 0 {
+            *self = World {
+                state: self.state,
+                frames: self.frames,
+                masks: self.masks.take(),
+                ..World::new(self.dims, self.seed, self.levels.clone(), config)
+            };
+            return;
+        }
+        self.player.physics = config.physics;
+        self.spawner.config = config.pipes;
+        self.spawner.physics = config.physics;
+        self.config = config;
+    }
+
+    pub fn update_state(&mut self, new_state: GameState) {
+        if self.state.is_paused() && new_state.is_playing() {
+            self.frames = 0;
+            self.state = GameState::Countdown;
+        } else {
+            self.state = new_state;
+        }
+    }
+
+    fn countdown(&mut self) {
+        self.frames += 1;
+        if self.frames > World::COUNTDOWN_TICKS {
+            self.state = GameState::Playing;
+        }
+    }
+
+    fn apply_input(&mut self, dt: f32, input: InputState) {
+        self.flap_timeout -= dt;
+        if input.flap && self.flap_timeout < 0. {
+            self.flap_timeout = self.config.physics.flap_timeout;
+            self.player.flap(dt);
+            self.events.push(Event::Flap(self.player_screen_pos()));
+        }
+        if self.input.flap && !input.flap {
+            self.player.release(dt);
+        }
+        self.input = input;
+    }
+
+    fn move_actors(&mut self, dt: f32) {
+        self.scroll(self.player.effects.time_scale());
+        self.move_player(dt);
+        // slow-mo slows the pipes' motion along with the scrolling
+        self.move_course(dt * self.player.effects.time_scale());
+    }
+
+    /// scroll the course on by a tick at the level's speed
+    fn scroll(&mut self, time_scale: f32) {
+        self.offset -=
+            self.config.physics.move_speed * self.levels.get(self.level).speed * time_scale;
+    }
+
+    fn move_player(&mut self, dt: f32) {
+        self.frames += 1;
+        self.ticks += 1;
+        self.player.effects.tick();
+        self.player.update(dt);
+    }
+
+    fn move_course(&mut self, dt: f32) {
+        for entity in &mut self.entities {
+            entity.update(dt);
+        }
+    }
+
+    /// Move a course shared by a flock on by a tick, without its bird:
+    /// scrolling, moving the pipes, then dropping and spawning them. The
+    /// birds fly through it with `fly_through`.
+    pub(crate) fn step_course(&mut self, dt: f32, time_scale: f32) {
+        self.scroll(time_scale);
+        self.move_course(dt * time_scale);
+        self.update_pipes();
+    }
+
+    /// Play a tick for the bird in a course that's moved on without it,
+    /// following `step` otherwise. The course is copied in first, pickups
+    /// taken are left as decorations for the caller to copy back.
+    pub(crate) fn fly_through(&mut self, course: &World, dt: f32, input: InputState) {
+        if !self.state.is_playing() {
+            self.step(dt, input);
+            return;
+        }
+        self.events.clear();
+        self.entities.clone_from(&course.entities);
+        self.offset = course.offset;
+        self.cleared = course.cleared;
+        self.apply_input(dt, input);
+        self.move_player(dt);
+        self.apply_bounds();
+        if self.state.is_playing() {
+            self.handle_collisions();
+        }
+        self.score_points();
+    }
+
+    fn score_points(&mut self) {
+        let passed = self.passed;
+        self.passed = self.pipes_passed();
+        if self.passed > passed {
+            self.score += (self.passed - passed) * self.player.effects.score_multiplier();
+            self.events.push(Event::Point(self.player_screen_pos()));
+        }
+        if self.state.is_playing() {
+            self.collect_pickups();
+        }
+
+        self.level_up = self.level_up.saturating_sub(1);
+        let level = self.levels.level_for(self.score);
+        if level > self.level {
+            self.level = level;
+            self.level_up = World::LEVEL_UP_TICKS;
+        }
+    }
+
+    fn update_pipes(&mut self) {
+        self.despawn();
+        self.spawner.fill(
+            &mut self.entities,
+            self.dims,
+            self.offset,
+            self.levels.get(self.level),
+            &mut self.rng,
+        );
+    }
+
+    pub fn pipes(&self) -> impl Iterator<Item = &Pipe> {
+        self.entities.iter().filter_map(Entity::as_pipe)
+    }
+
+    /// the first pair the bird hasn't got all the way past, bottom then top
+    pub fn next_pair(&self) -> Option<(&Pipe, &Pipe)> {
+        let x = self.player_pos().x - self.player.bbox_size.x;
+        let bottom = self
+            .pipes()
+            .find(|p| !p.is_top() && p.pos.x + p.bbox_size.x > x)?;
+        let top = self
+            .pipes()
+            .find(|p| p.is_top() && p.rest.x == bottom.rest.x)?;
+        Some((bottom, top))
+    }
+
+    pub fn player_screen_pos(&self) -> Point2<f32> {
+        translate_coords(
+            self.player.pos,
+            self.dims.screen_width,
+            self.dims.screen_height,
+        )
+    }
+
+    /// player position in the same space as the pipes
+    pub fn player_pos(&self) -> Point2<f32> {
+        let mut pos = self.player_screen_pos();
+        pos.x -= self.offset;
+        pos
+    }
+
+    /// drop entities that are done with, counting the pipe pairs that go
+    fn despawn(&mut self) {
+        let offset = self.offset;
+        let pairs = |world: &World| world.pipes().filter(|p| !p.is_top()).count();
+        let before = pairs(self);
+        self.entities.retain(|e| !e.despawned(offset));
+        self.cleared += before - pairs(self);
+    }
+
+    /// bottom of the bird, in screen space
+    fn player_bottom(&self) -> f32 {
+        self.player_screen_pos().y + self.player.bbox_size.y
+    }
+
+    /// move the bird so its bottom is at `y` in screen space
+    fn set_player_bottom(&mut self, y: f32) {
+        self.player.pos.y = self.dims.screen_height / 2. - (y - self.player.bbox_size.y);
+    }
+
+    /// has the bird come to rest on the ground
+    pub fn landed(&self) -> bool {
+        self.player_bottom() >= self.dims.ground && self.player.velocity.y == 0.
+    }
+
+    /// The top of the screen and the top of the base are the edges of the
+    /// world. The ceiling does what the config says, the ground is always
+    /// the end of the run.
+    fn apply_bounds(&mut self) {
+        if self.player_bottom() >= self.dims.ground {
+            self.set_player_bottom(self.dims.ground);
+            self.update_state(GameState::GameOver);
+            self.events.push(Event::Hit(self.player_screen_pos()));
+            return;
+        }
+
+        let player_top = self.player_screen_pos().y - self.player.bbox_size.y;
+        // velocity is in world space, so up is positive
+        if player_top > 0. || self.player.velocity.y <= 0. {
+            return;
+        }
+        match self.config.bounds.ceiling {
+            Ceiling::Clamp => {
+                self.set_player_bottom(2. * self.player.bbox_size.y);
+                self.player.velocity.y = 0.;
+            }
+            Ceiling::Bounce => {
+                self.set_player_bottom(2. * self.player.bbox_size.y);
+                self.player.velocity.y *= -World::BOUNCE;
+            }
+            Ceiling::Death => {
+                self.update_state(GameState::GameOver);
+                self.events.push(Event::Hit(self.player_screen_pos()));
+            }
+        }
+    }
+
+    /// drop the bird onto the ground after a crash, nose first
+    fn land(&mut self, dt: f32) {
+        self.player.update(dt);
+        if self.player_bottom() >= self.dims.ground {
+            self.set_player_bottom(self.dims.ground);
+            self.player.velocity.y = 0.;
+            self.player.facing = self.config.physics.down_angle_max;
+        }
+    }
+
+    fn shape_params(&self) -> ShapeParams<'_> {
+        ShapeParams {
+            kinds: self.config.collision,
+            masks: self.masks.as_deref(),
+            // pipes run on off the screen, however far the bird gets
+            reach: self.dims.screen_height,
+        }
+    }
+
+    /// entities the bird is touching, by index, with what touching them does
+    fn contacts(&self) -> Vec<(usize, Contact)> {
+        let params = self.shape_params();
+        let bird = self.player.shape_at(self.player_pos(), params);
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entity)| {
+                let contact = entity.contact()?;
+                let shape = entity.shape(params)?;
+                if bird.intersects(&shape) {
+                    Some((i, contact))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn handle_collisions(&mut self) {
+        let hit = self
+            .contacts()
+            .iter()
+            .any(|&(_, contact)| contact == Contact::Solid);
+        if hit && !self.player.effects.absorb_hit() {
+            self.update_state(GameState::GameOver);
+            self.events.push(Event::Hit(self.player_screen_pos()));
+        }
+    }
+
+    /// pick up the apples and power-ups the bird touches, leaving them to
+    /// float away as decorations
+    fn collect_pickups(&mut self) {
+        for (i, contact) in self.contacts() {
+            let pos = self.player_screen_pos();
+            match contact {
+                Contact::Solid => continue,
+                Contact::Apple(kind) => {
+                    let bonus = kind.bonus() * self.player.effects.score_multiplier();
+                    self.bonus += bonus;
+                    self.score += bonus;
+                    self.events.push(Event::Collect(pos, kind));
+                }
+                Contact::PowerUp(kind) => {
+                    self.player.effects.add(kind);
+                    self.events.push(Event::PowerUp(pos, kind));
+                }
+            }
+            let entity = &self.entities[i];
+            let decoration =
+                Decoration::floating(entity.sprite(), entity.pos(), entity.bbox_size());
+            self.entities[i] = Entity::Decoration(decoration);
+        }
+    }
+
+    /// pipes the bird has got past, including ones that have been cleared
+    fn pipes_passed(&self) -> usize {
+        let player_x = self.player_pos().x;
+        self.cleared
+            + self
+                .pipes()
+                .filter(|p| !p.is_top() && p.pos.x < player_x)
+                .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actors::Apple;
+
+    const DT: f32 = 1. / crate::DESIRED_FPS as f32;
+
+    /// a world in play, past the countdown
+    fn playing(config: Config) -> World {
+        let mut world = World::new(
+            Dimensions::default(),
+            GameSeed(1),
+            Levels::default(),
+            config,
+        );
+        world.state = GameState::Playing;
+        world
+    }
+
+    /// a world in play with the bird rising into the ceiling
+    fn at_the_ceiling(ceiling: Ceiling) -> World {
+        let mut config = Config::default();
+        config.bounds.ceiling = ceiling;
+        let mut world = playing(config);
+        world.player.pos.y = world.dims.screen_height / 2. + 10.;
+        world.player.velocity.y = 5.;
+        world
+    }
+
+    fn hit(world: &World) -> bool {
+        world.events.iter().any(|e| matches!(e, Event::Hit(_)))
+    }
+
+    /// bonus from flying into an apple of `kind`, with `effects` on
+    fn collect(kind: AppleKind, effects: &[PowerUpKind]) -> usize {
+        let mut world = playing(Config::default());
+        for &effect in effects {
+            world.player.effects.add(effect);
+        }
+        world.entities.clear();
+        world
+            .entities
+            .push(Entity::Apple(Apple::new(world.player_pos(), kind)));
+        world.step(DT, InputState::default());
+        assert!(world
+            .events
+            .iter()
+            .any(|e| matches!(e, Event::Collect(_, k) if *k == kind)));
+        // eaten, it floats off out of reach
+        assert!(matches!(world.entities[0], Entity::Decoration(_)));
+        assert_eq!(world.score, world.bonus);
+        world.score
+    }
+
+    #[test]
+    fn apples_score_by_kind() {
+        assert_eq!(collect(AppleKind::Green, &[]), 1);
+        assert_eq!(collect(AppleKind::Red, &[]), 2);
+        assert_eq!(collect(AppleKind::Purple, &[]), 5);
+        assert_eq!(collect(AppleKind::Purple, &[PowerUpKind::Multiplier]), 10);
+    }
+
+    #[test]
+    fn the_ceiling_clamps() {
+        let mut world = at_the_ceiling(Ceiling::Clamp);
+        world.step(DT, InputState::default());
+        assert!(world.state.is_playing());
+        assert_eq!(world.player.velocity.y, 0.);
+        let top = world.player_screen_pos().y - world.player.bbox_size.y;
+        assert!(top.abs() < 1e-3, "top of the bird at {}", top);
+    }
+
+    #[test]
+    fn the_ceiling_bounces() {
+        let mut world = at_the_ceiling(Ceiling::Bounce);
+        let rising = 5. - world.config.physics.fall_speed * DT;
+        world.step(DT, InputState::default());
+        assert!(world.state.is_playing());
+        assert!(!hit(&world));
+        let expected = -rising * World::BOUNCE;
+        assert!((world.player.velocity.y - expected).abs() < 1e-4);
+        let top = world.player_screen_pos().y - world.player.bbox_size.y;
+        assert!(top.abs() < 1e-3, "top of the bird at {}", top);
+    }
+
+    #[test]
+    fn the_ceiling_kills() {
+        let mut world = at_the_ceiling(Ceiling::Death);
+        world.step(DT, InputState::default());
+        assert!(world.state.is_gameover());
+        assert!(hit(&world));
+    }
+
+    #[test]
+    fn a_crashed_bird_lands_nose_first() {
+        let mut world = at_the_ceiling(Ceiling::Death);
+        world.step(DT, InputState::default());
+        assert!(!world.landed());
+        let ticks = world.ticks;
+        for _ in 0..600 {
+            world.step(DT, InputState::default());
+        }
+        assert!(world.landed());
+        assert_eq!(world.player_bottom(), world.dims.ground);
+        assert_eq!(world.player.facing, world.config.physics.down_angle_max);
+        // the run's clock stopped at the crash
+        assert_eq!(world.ticks, ticks);
+
+        // and once down it stays put
+        let pos = world.player.pos;
+        world.step(DT, InputState::default());
+        assert_eq!(world.player.pos, pos);
+    }
+
+    #[test]
+    fn the_ground_ends_the_run() {
+        let mut world = at_the_ceiling(Ceiling::Clamp);
+        world.player.velocity.y = 0.;
+        let mut ticks = 0;
+        while world.state.is_playing() && ticks < 600 {
+            world.step(DT, InputState::default());
+            ticks += 1;
+        }
+        assert!(world.state.is_gameover());
+        assert!(hit(&world));
+        assert_eq!(world.player_bottom(), world.dims.ground);
+    }
+}